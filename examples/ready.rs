@@ -24,7 +24,7 @@ async fn main() -> Result<()> {
     let client = Discord::new();
 
     {
-        let mut client_events = client.borrow_mut();
+        let client_events = client.borrow();
         client_events.register_event(
             events::READY,
             ExampleHandler {
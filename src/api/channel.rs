@@ -14,6 +14,7 @@ pub struct Channel {
     pub kind: ChannelType,
     pub guild_id: Option<GuildId>,
     pub position: Option<u64>,
+    #[serde(default)]
     pub permission_overwrites: Vec<PermissionOverwrite>,
     pub name: Option<String>,
     pub topic: Option<String>,
@@ -65,7 +66,8 @@ pub struct Message {
     pub kind: MessageType,
 
     pub author: Option<User>,
-    pub member: Option<super::guild::GuildMember>,
+    /// Only sent with gateway events of messages in guilds
+    pub member: Option<super::guild::PartialGuildMember>,
 
     pub content: String,
     pub timestamp: String,
@@ -74,9 +76,11 @@ pub struct Message {
     pub tts: bool,
     pub pinned: bool,
 
+    #[serde(rename = "mention_everyone")]
     pub mentions_everyone: bool,
     pub mentions: Vec<User>, // includes partial member?
     pub mention_roles: Vec<RoleId>,
+    #[serde(default)]
     pub mention_channels: Vec<ChannelMention>,
 
     pub attachments: Vec<Attachment>,
@@ -87,10 +91,153 @@ pub struct Message {
     pub webhook_id: Option<Id>,
     pub activity: Option<MessageActivity>,
     pub message_reference: Option<MessageReference>,
+    #[serde(default, deserialize_with = "string_or_integer")]
     pub nonce: Option<String>,
     pub flags: Option<u8>,
 }
 
+/// A message from a MESSAGE_UPDATE event. Only the id and channel
+/// are always sent, everything else is only sent if it changed.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PartialMessage {
+    pub id: MessageId,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+    #[serde(rename = "type")]
+    pub kind: Option<MessageType>,
+
+    pub author: Option<User>,
+    pub member: Option<super::guild::PartialGuildMember>,
+
+    pub content: Option<String>,
+    pub timestamp: Option<String>,
+    pub edited_timestamp: Option<String>,
+
+    pub tts: Option<bool>,
+    pub pinned: Option<bool>,
+
+    #[serde(rename = "mention_everyone")]
+    pub mentions_everyone: Option<bool>,
+    pub mentions: Option<Vec<User>>,
+    pub mention_roles: Option<Vec<RoleId>>,
+    pub mention_channels: Option<Vec<ChannelMention>>,
+
+    pub attachments: Option<Vec<Attachment>>,
+    pub embeds: Option<Vec<Embed>>,
+    pub reactions: Option<Vec<Reaction>>,
+
+    pub webhook_id: Option<Id>,
+    pub activity: Option<MessageActivity>,
+    pub message_reference: Option<MessageReference>,
+    pub flags: Option<u8>,
+}
+
+/// A message that was deleted, from a MESSAGE_DELETE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-delete
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeletedMessage {
+    pub id: MessageId,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+}
+
+/// Multiple messages that were deleted at once, from a MESSAGE_DELETE_BULK event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-delete-bulk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeletedMessages {
+    pub ids: Vec<MessageId>,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+}
+
+/// A message was pinned or unpinned in a channel, from a CHANNEL_PINS_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#channel-pins-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChannelPins {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub last_pin_timestamp: Option<String>,
+}
+
+/// A user started typing in a channel, from a TYPING_START event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#typing-start
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Typing {
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+    pub user_id: UserId,
+    /// Unix time (in seconds) of when the user started typing
+    pub timestamp: u64,
+    /// Only sent when typing in a guild
+    pub member: Option<super::guild::GuildMember>,
+}
+
+/// A reaction that was added to or removed from a message, from a
+/// MESSAGE_REACTION_ADD or MESSAGE_REACTION_REMOVE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-reaction-add
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MessageReaction {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub guild_id: Option<GuildId>,
+    /// Only sent when a reaction is added in a guild
+    pub member: Option<super::guild::GuildMember>,
+    /// Only the id, name and animated fields are sent
+    pub emoji: Emoji,
+}
+
+/// Every reaction was removed from a message, from a MESSAGE_REACTION_REMOVE_ALL event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-reaction-remove-all
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClearedReactions {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub guild_id: Option<GuildId>,
+}
+
+/// Every reaction with an emoji was removed from a message, from a
+/// MESSAGE_REACTION_REMOVE_EMOJI event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#message-reaction-remove-emoji
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClearedEmojiReactions {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub guild_id: Option<GuildId>,
+    pub emoji: Emoji,
+}
+
+/// Nonces are sent as whatever the client that sent the message used,
+/// which can be either a string or an integer.
+fn string_or_integer<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let nonce = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match nonce {
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
 #[derive(Serialize_repr, Deserialize_repr, Debug, Clone, PartialEq, Eq)]
 #[repr(u8)]
 #[non_exhaustive]
@@ -73,11 +73,22 @@ pub(crate) struct BotGatewayResponse {
     pub shards: u32,
     pub session_start_limit: SessionStartLimit,
 }
-#[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct SessionStartLimit {
+/// How many times a bot can still identify with the gateway.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#session-start-limit-object
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStartLimit {
     pub total: u32,
     pub remaining: u32,
+    /// Milliseconds until `remaining` resets to `total`
     pub reset_after: u32,
+    /// How many shards can identify at the same time
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: u32,
+}
+
+fn default_max_concurrency() -> u32 {
+    1
 }
 
 /// Gets the url for the wss gateway that a bot should use when connecting
@@ -86,8 +97,59 @@ pub(crate) struct SessionStartLimit {
 pub(crate) async fn get_bot_gateway(token: &str) -> Result<BotGatewayResponse, surf::Exception> {
     let gateway = surf::get(format!("{}/gateway/bot", crate::discord::API_PATH))
         .set_header("Authorization", format!("Bot {}", token))
-        .set_header("User-Agent", crate::discord::USER_AGENT.to_owned())
+        .set_header("User-Agent", crate::discord::USER_AGENT)
         .recv_json()
         .await?;
     Ok(gateway)
 }
+
+/// The data of the READY event, which a shard receives after identifying.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#ready
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Ready {
+    /// The gateway version
+    #[serde(rename = "v")]
+    pub version: u8,
+    pub user: super::user::User,
+    /// The guilds of the shard, which are unavailable until their
+    /// GUILD_CREATE events arrive
+    pub guilds: Vec<super::guild::UnavailableGuild>,
+    pub session_id: String,
+    /// The `[shard_id, num_shards]` of the shard
+    pub shard: Option<[u32; 2]>,
+}
+
+/// The presence of the bot, sent with op 3 or in the Identify payload.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#update-status
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct StatusUpdate {
+    /// Unix time (in milliseconds) of when the client went idle
+    pub since: Option<u64>,
+    #[serde(serialize_with = "serialize_bot_activity")]
+    pub game: Option<super::guild::Activity>,
+    pub status: super::guild::OnlineStatus,
+    pub afk: bool,
+}
+
+/// Bots can only set the name, type and url of their activity.
+fn serialize_bot_activity<S>(
+    activity: &Option<super::guild::Activity>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    activity
+        .as_ref()
+        .map(|a| {
+            serde_json::json!({
+                "name": a.name,
+                "type": a.kind,
+                "url": a.url,
+            })
+        })
+        .serialize(serializer)
+}
@@ -86,6 +86,91 @@ pub struct GuildMember {
     pub mute: bool,
 }
 
+/// A user that joined a guild, from a GUILD_MEMBER_ADD event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-member-add
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NewGuildMember {
+    pub guild_id: GuildId,
+    #[serde(flatten)]
+    pub member: GuildMember,
+}
+
+/// A guild member that changed, from a GUILD_MEMBER_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-member-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UpdatedGuildMember {
+    pub guild_id: GuildId,
+    pub roles: Vec<RoleId>,
+    pub user: super::user::User,
+    pub nick: Option<String>,
+    pub premium_since: Option<String>,
+}
+
+/// A user that left or was removed from a guild, from a GUILD_MEMBER_REMOVE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-member-remove
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RemovedGuildMember {
+    pub guild_id: GuildId,
+    pub user: super::user::User,
+}
+
+/// A guild member without the user, which is sent along with
+/// messages in MESSAGE_CREATE and MESSAGE_UPDATE events.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PartialGuildMember {
+    pub nick: Option<String>,
+    pub roles: Vec<RoleId>,
+    pub joined_at: String,
+    pub premium_since: Option<String>,
+    #[serde(default)]
+    pub deaf: bool,
+    #[serde(default)]
+    pub mute: bool,
+}
+
+/// A part of the members sent in response to a Request Guild Members command
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-members-chunk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct MemberChunk {
+    pub guild_id: GuildId,
+    pub members: Vec<GuildMember>,
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    pub not_found: Option<Vec<UserId>>,
+    pub presences: Option<Vec<PresenceUpdate>>,
+    pub nonce: Option<String>,
+}
+
+/// Which members to request with `Discord::request_guild_members`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemberQuery {
+    /// Members whose username starts with the string, or every
+    /// member if it is empty.
+    Query(String),
+    /// Only the members with the given ids.
+    UserIds(Vec<UserId>),
+}
+
+/// Every member sent in response to a `Discord::request_guild_members` call
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct RequestedMembers {
+    pub members: Vec<GuildMember>,
+    /// Only sent if presences were requested
+    pub presences: Vec<PresenceUpdate>,
+    /// The requested user ids that aren't members of the guild
+    pub not_found: Vec<UserId>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub struct Role {
@@ -99,6 +184,50 @@ pub struct Role {
     pub mentionable: bool,
 }
 
+/// A role that was created or changed, from a GUILD_ROLE_CREATE or
+/// GUILD_ROLE_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-role-create
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GuildRole {
+    pub guild_id: GuildId,
+    pub role: Role,
+}
+
+/// A role that was deleted, from a GUILD_ROLE_DELETE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-role-delete
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeletedRole {
+    pub guild_id: GuildId,
+    pub role_id: RoleId,
+}
+
+/// Every emoji of a guild after one of them changed, from a GUILD_EMOJIS_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-emojis-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GuildEmojis {
+    pub guild_id: GuildId,
+    pub emojis: Vec<super::channel::Emoji>,
+}
+
+/// A guild the bot left or was removed from, or that became unavailable
+/// because of an outage, from a GUILD_DELETE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-delete
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnavailableGuild {
+    pub id: GuildId,
+    /// Set if the guild is unavailable, and not set if the bot was removed from it
+    #[serde(default)]
+    pub unavailable: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct PresenceUpdate {
@@ -108,6 +237,9 @@ pub struct PresenceUpdate {
     pub guild_id: Option<GuildId>,
     pub status: Option<OnlineStatus>,
     pub activities: Option<Vec<Activity>>,
+    pub client_status: Option<ClientStatus>,
+    pub premium_since: Option<String>,
+    pub nick: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -119,6 +251,9 @@ pub enum OnlineStatus {
     Idle,
     #[serde(rename = "dnd")]
     Dnd,
+    /// Only used when setting the status of the bot, it shows as offline to others
+    #[serde(rename = "invisible")]
+    Invisible,
     #[serde(rename = "offline")]
     Offline,
 }
@@ -139,7 +274,7 @@ pub struct Activity {
     pub kind: ActivityType,
     pub url: Option<String>,
     pub created_at: u64,
-    pub timestamps: Option<Vec<ActivityTimestamp>>,
+    pub timestamps: Option<ActivityTimestamp>,
     pub application_id: Option<ApplicationId>,
     pub details: Option<String>,
     pub state: Option<String>,
@@ -153,6 +288,43 @@ pub struct Activity {
     pub flags: Option<u64>,
 }
 
+impl Activity {
+    /// Creates an activity with the given type and name, to use as the
+    /// presence of the bot.
+    pub fn new(kind: ActivityType, name: &str) -> Self {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        Activity {
+            name: name.to_owned(),
+            kind,
+            url: None,
+            created_at,
+            timestamps: None,
+            application_id: None,
+            details: None,
+            state: None,
+            emoji: None,
+            party: None,
+            assets: None,
+            secrets: None,
+            instance: None,
+            flags: None,
+        }
+    }
+
+    /// Creates a "Streaming" activity. The url has to be a twitch.tv
+    /// or youtube.com url.
+    pub fn streaming(name: &str, url: &str) -> Self {
+        Activity {
+            url: Some(url.to_owned()),
+            ..Self::new(ActivityType::Streaming, name)
+        }
+    }
+}
+
 #[derive(Serialize_repr, Deserialize_repr, Debug, Clone, PartialEq, Eq)]
 #[repr(u8)]
 #[non_exhaustive]
@@ -160,6 +332,7 @@ pub enum ActivityType {
     Game = 0,
     Streaming = 1,
     Listening = 2,
+    Watching = 3,
     Custom = 4,
 }
 
@@ -201,7 +374,8 @@ pub struct Invite {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub struct InviteMetadata {
-    pub inviter: super::user::User,
+    /// Not set for invites that weren't created by a user, like vanity urls
+    pub inviter: Option<super::user::User>,
     pub uses: u64,
     pub max_uses: u64,
     pub max_age: u64,
@@ -224,7 +398,64 @@ pub struct VoiceRegion {
 #[non_exhaustive]
 pub struct Ban {
     pub reason: Option<String>,
-    pub user: UserId,
+    pub user: super::user::User,
+}
+
+/// A user that was banned or unbanned, from a GUILD_BAN_ADD or
+/// GUILD_BAN_REMOVE event. The reason is never sent with the event.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-ban-add
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GuildBan {
+    pub guild_id: GuildId,
+    #[serde(flatten)]
+    pub ban: Ban,
+}
+
+/// An invite that was created, from an INVITE_CREATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#invite-create
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CreatedInvite {
+    pub code: String,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+    pub target_user: Option<super::user::User>,
+    pub target_user_type: Option<u64>,
+    #[serde(flatten)]
+    pub metadata: InviteMetadata,
+}
+
+/// An invite that was deleted or expired, from an INVITE_DELETE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#invite-delete
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeletedInvite {
+    pub code: String,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+}
+
+/// The webhooks of a channel changed, from a WEBHOOKS_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#webhooks-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UpdatedWebhooks {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+}
+
+/// The integrations of a guild changed, from a GUILD_INTEGRATIONS_UPDATE event
+///
+/// https://discordapp.com/developers/docs/topics/gateway#guild-integrations-update
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UpdatedIntegrations {
+    pub guild_id: GuildId,
 }
 
 pub async fn get_invite(token: &str, invite: &str) -> Result<Invite> {
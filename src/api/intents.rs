@@ -0,0 +1,76 @@
+bitflags::bitflags! {
+    /// The groups of gateway events a client wants to receive.
+    ///
+    /// https://discordapp.com/developers/docs/topics/gateway#gateway-intents
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(transparent)]
+    pub struct GatewayIntents: u64 {
+        const GUILDS                   = 1 << 0;
+        /// Privileged, has to be enabled in the developer portal
+        const GUILD_MEMBERS            = 1 << 1;
+        const GUILD_BANS               = 1 << 2;
+        const GUILD_EMOJIS             = 1 << 3;
+        const GUILD_INTEGRATIONS       = 1 << 4;
+        const GUILD_WEBHOOKS           = 1 << 5;
+        const GUILD_INVITES            = 1 << 6;
+        const GUILD_VOICE_STATES       = 1 << 7;
+        /// Privileged, has to be enabled in the developer portal
+        const GUILD_PRESENCES          = 1 << 8;
+        const GUILD_MESSAGES           = 1 << 9;
+        const GUILD_MESSAGE_REACTIONS  = 1 << 10;
+        const GUILD_MESSAGE_TYPING     = 1 << 11;
+        const DIRECT_MESSAGES          = 1 << 12;
+        const DIRECT_MESSAGE_REACTIONS = 1 << 13;
+        const DIRECT_MESSAGE_TYPING    = 1 << 14;
+    }
+}
+
+impl GatewayIntents {
+    /// The intents that have to be enabled in the developer portal
+    /// before they can be used.
+    pub fn privileged() -> Self {
+        Self::GUILD_MEMBERS | Self::GUILD_PRESENCES
+    }
+
+    /// Every intent that can be used without enabling it in the
+    /// developer portal.
+    pub fn non_privileged() -> Self {
+        Self::all() - Self::privileged()
+    }
+
+    /// Gets the intents that cause the given event to be sent.
+    /// Enabling any of them is enough to receive the event.
+    ///
+    /// Returns `None` for events that are sent regardless of intents.
+    pub fn for_event(event: &str) -> Option<Self> {
+        let intents = match event {
+            "GUILD_CREATE" | "GUILD_UPDATE" | "GUILD_DELETE" | "GUILD_ROLE_CREATE"
+            | "GUILD_ROLE_UPDATE" | "GUILD_ROLE_DELETE" | "CHANNEL_CREATE" | "CHANNEL_UPDATE"
+            | "CHANNEL_DELETE" => Self::GUILDS,
+            "CHANNEL_PINS_UPDATE" => Self::GUILDS | Self::DIRECT_MESSAGES,
+            "GUILD_MEMBER_ADD" | "GUILD_MEMBER_UPDATE" | "GUILD_MEMBER_REMOVE" => {
+                Self::GUILD_MEMBERS
+            }
+            "GUILD_BAN_ADD" | "GUILD_BAN_REMOVE" => Self::GUILD_BANS,
+            "GUILD_EMOJIS_UPDATE" => Self::GUILD_EMOJIS,
+            "GUILD_INTEGRATIONS_UPDATE" => Self::GUILD_INTEGRATIONS,
+            "WEBHOOKS_UPDATE" => Self::GUILD_WEBHOOKS,
+            "INVITE_CREATE" | "INVITE_DELETE" => Self::GUILD_INVITES,
+            "VOICE_STATE_UPDATE" => Self::GUILD_VOICE_STATES,
+            "PRESENCE_UPDATE" => Self::GUILD_PRESENCES,
+            "MESSAGE_CREATE" | "MESSAGE_UPDATE" | "MESSAGE_DELETE" => {
+                Self::GUILD_MESSAGES | Self::DIRECT_MESSAGES
+            }
+            "MESSAGE_DELETE_BULK" => Self::GUILD_MESSAGES,
+            "MESSAGE_REACTION_ADD"
+            | "MESSAGE_REACTION_REMOVE"
+            | "MESSAGE_REACTION_REMOVE_ALL"
+            | "MESSAGE_REACTION_REMOVE_EMOJI" => {
+                Self::GUILD_MESSAGE_REACTIONS | Self::DIRECT_MESSAGE_REACTIONS
+            }
+            "TYPING_START" => Self::GUILD_MESSAGE_TYPING | Self::DIRECT_MESSAGE_TYPING,
+            _ => return None,
+        };
+        Some(intents)
+    }
+}
@@ -12,6 +12,7 @@ pub mod channel;
 pub mod gateway;
 pub mod guild;
 pub mod id;
+pub mod intents;
 pub mod permissions;
 pub mod user;
 
@@ -27,7 +28,7 @@ fn set_headers<C: surf::middleware::HttpClient>(
 ) -> surf::Request<C> {
     request
         .set_header("Authorization", format!("Bot {}", token))
-        .set_header("User-Agent", crate::discord::USER_AGENT.to_owned())
+        .set_header("User-Agent", crate::discord::USER_AGENT)
 }
 
 /// Makes an http GET request with a token and a url starting after `/api/v6/`
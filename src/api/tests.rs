@@ -1,7 +1,7 @@
-use super::channel::*;
 use super::guild::*;
 use super::id::*;
-use super::user::*;
+use super::intents::GatewayIntents;
+use super::permissions::Permissions;
 
 #[test]
 fn test_role() {
@@ -25,7 +25,7 @@ fn test_role() {
             color: 3447003,
             hoist: true,
             position: 1,
-            permissions: 66321471,
+            permissions: Permissions::from_bits_truncate(66321471),
             managed: false,
             mentionable: false,
         }
@@ -79,8 +79,8 @@ fn test_guild() {
             mfa_level: Some(0),
             widget_enabled: false,
             widget_channel_id: Some(ChannelId(Id(41771983423143937))),
-            roles: vec![],
-            emojis: vec![],
+            roles: Some(vec![]),
+            emojis: Some(vec![]),
             features: vec![String::from("INVITE_SPLASH")],
             unavailable: Some(false),
 
@@ -106,3 +106,192 @@ fn test_guild() {
         }
     );
 }
+
+#[test]
+fn test_intents_for_event() {
+    assert_eq!(
+        GatewayIntents::for_event("MESSAGE_CREATE"),
+        Some(GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES)
+    );
+    assert_eq!(
+        GatewayIntents::for_event("PRESENCE_UPDATE"),
+        Some(GatewayIntents::GUILD_PRESENCES)
+    );
+    assert_eq!(GatewayIntents::for_event("READY"), None);
+    assert!(!GatewayIntents::non_privileged().intersects(GatewayIntents::privileged()));
+}
+
+#[test]
+fn test_status_update() {
+    use super::gateway::StatusUpdate;
+    use super::guild::{Activity, OnlineStatus};
+
+    let presence = StatusUpdate {
+        since: None,
+        game: Some(Activity::streaming(
+            "thatcord",
+            "https://twitch.tv/thatcord",
+        )),
+        status: OnlineStatus::Dnd,
+        afk: false,
+    };
+    assert_eq!(
+        serde_json::to_value(&presence).unwrap(),
+        serde_json::json!({
+            "since": null,
+            "game": {"name": "thatcord", "type": 1, "url": "https://twitch.tv/thatcord"},
+            "status": "dnd",
+            "afk": false,
+        })
+    );
+}
+
+#[test]
+fn test_message_events() {
+    use super::channel::{DeletedMessages, Message, PartialMessage};
+
+    let message: Message = serde_json::from_value(serde_json::json!({
+        "id": "334385199974967042",
+        "channel_id": "290926798999357250",
+        "guild_id": "290926798626357999",
+        "type": 0,
+        "author": {
+            "id": "53908099506183680",
+            "username": "Mason",
+            "discriminator": "9999",
+            "avatar": "a_bab14f271d565501444b2ca3be944b25"
+        },
+        "member": {
+            "roles": [],
+            "joined_at": "2017-03-13T19:19:14.040000+00:00",
+            "deaf": false,
+            "mute": false
+        },
+        "content": "Supa Hot",
+        "timestamp": "2017-07-11T17:27:07.299000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "pinned": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "nonce": 1234
+    }))
+    .unwrap();
+    assert_eq!(message.content, "Supa Hot");
+    assert_eq!(message.nonce.as_deref(), Some("1234"));
+    assert!(message.member.is_some());
+
+    let update: PartialMessage = serde_json::from_value(serde_json::json!({
+        "id": "334385199974967042",
+        "channel_id": "290926798999357250",
+        "embeds": []
+    }))
+    .unwrap();
+    assert_eq!(update.content, None);
+    assert_eq!(update.embeds, Some(vec![]));
+
+    let deleted: DeletedMessages = serde_json::from_value(serde_json::json!({
+        "ids": ["334385199974967042", "334385199974967043"],
+        "channel_id": "290926798999357250"
+    }))
+    .unwrap();
+    assert_eq!(deleted.ids.len(), 2);
+    assert_eq!(deleted.guild_id, None);
+}
+
+#[test]
+fn test_reaction_events() {
+    use super::channel::{ClearedEmojiReactions, MessageReaction};
+
+    let reaction: MessageReaction = serde_json::from_value(serde_json::json!({
+        "user_id": "53908099506183680",
+        "channel_id": "290926798999357250",
+        "message_id": "334385199974967042",
+        "guild_id": "290926798626357999",
+        "member": {
+            "user": {
+                "id": "53908099506183680",
+                "username": "Mason",
+                "discriminator": "9999",
+                "avatar": null
+            },
+            "roles": [],
+            "joined_at": "2017-03-13T19:19:14.040000+00:00",
+            "deaf": false,
+            "mute": false
+        },
+        "emoji": {"id": null, "name": "🔥"}
+    }))
+    .unwrap();
+    assert_eq!(reaction.emoji.name.as_deref(), Some("🔥"));
+    assert_eq!(reaction.member.unwrap().user.username, "Mason");
+
+    let cleared: ClearedEmojiReactions = serde_json::from_value(serde_json::json!({
+        "channel_id": "290926798999357250",
+        "message_id": "334385199974967042",
+        "emoji": {"id": "41771983429993937", "name": "LUL", "animated": true}
+    }))
+    .unwrap();
+    assert_eq!(cleared.emoji.animated, Some(true));
+}
+
+#[test]
+fn test_moderation_events() {
+    let ban: GuildBan = serde_json::from_value(serde_json::json!({
+        "guild_id": "290926798626357999",
+        "user": {
+            "id": "53908099506183680",
+            "username": "Mason",
+            "discriminator": "9999",
+            "avatar": null
+        }
+    }))
+    .unwrap();
+    assert_eq!(ban.ban.reason, None);
+    assert_eq!(ban.ban.user.username, "Mason");
+
+    let invite: CreatedInvite = serde_json::from_value(serde_json::json!({
+        "channel_id": "290926798999357250",
+        "code": "0vCdhLbwjZZTWZLD",
+        "created_at": "2016-06-11T23:06:34.136000+00:00",
+        "guild_id": "290926798626357999",
+        "max_age": 86400,
+        "max_uses": 0,
+        "temporary": false,
+        "uses": 0
+    }))
+    .unwrap();
+    assert_eq!(invite.metadata.inviter, None);
+    assert_eq!(invite.metadata.max_age, 86400);
+}
+
+#[test]
+fn test_presence_update() {
+    let presence: PresenceUpdate = serde_json::from_value(serde_json::json!({
+        "user": {"id": "53908099506183680"},
+        "roles": [],
+        "game": null,
+        "guild_id": "290926798626357999",
+        "status": "online",
+        "activities": [{
+            "name": "a movie",
+            "type": 3,
+            "created_at": 1590000000000u64,
+            "timestamps": {"start": 1590000000000u64}
+        }],
+        "client_status": {"desktop": "online", "mobile": "idle"}
+    }))
+    .unwrap();
+    assert_eq!(presence.status, Some(OnlineStatus::Online));
+
+    let client_status = presence.client_status.unwrap();
+    assert_eq!(client_status.mobile, Some(OnlineStatus::Idle));
+    assert_eq!(client_status.web, None);
+
+    let activity = &presence.activities.unwrap()[0];
+    assert_eq!(activity.kind, ActivityType::Watching);
+    assert_eq!(activity.timestamps.as_ref().unwrap().end, None);
+}
@@ -1,11 +1,26 @@
 use crate::api;
 use crate::errors::*;
 use crate::events::*;
-use crate::gateway::{EventHandler as GatewayEventHandler, Gateway};
+use crate::gateway::encoding::Encoding;
+use crate::gateway::identify::LocalIdentifyQueue;
+use crate::gateway::shard::Shards;
+use crate::gateway::status::ShardStatus;
+use crate::gateway::{EventDispatcher, GatewayConfig, IdentifyQueue, ShardInfo, ShardManager};
 use async_trait::async_trait;
-use std::cell::RefCell;
+use futures_util::future::FutureExt;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::{Rc, Weak};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+
+mod handle;
+#[cfg(test)]
+mod tests;
+
+pub use handle::DiscordHandle;
 
 pub(crate) const API_PATH: &str = "https://discordapp.com/api/v6";
 
@@ -19,10 +34,42 @@ pub(crate) const USER_AGENT: &str = concat!(
     ")"
 );
 
+/// An event handler, and where it runs among the other handlers of its event.
+///
+/// Handlers registered through a `DiscordHandle` are `Send`, so they can be
+/// queued from other threads.
+struct RegisteredHandler<H: ?Sized = dyn EventHandler> {
+    id: HandlerId,
+    priority: i32,
+    handler: Box<H>,
+}
+
+/// Adds a handler after every handler with the same or a higher priority.
+fn insert_handler(handlers: &mut Vec<RegisteredHandler>, handler: RegisteredHandler) {
+    let index = handlers
+        .iter()
+        .position(|h| h.priority < handler.priority)
+        .unwrap_or(handlers.len());
+    handlers.insert(index, handler);
+}
+
+/// A change to the registered handlers, which is applied before the next
+/// handler runs.
+enum HandlerChange<H: ?Sized = dyn EventHandler> {
+    /// Adds a handler for an event, or for every event if there is no event
+    Register {
+        event: Option<String>,
+        handler: RegisteredHandler<H>,
+    },
+    Unregister(HandlerId),
+}
+
 /// A structure that handles distributing events to different event
 /// handlers.
 struct DefaultEventHandler {
-    events: HashMap<String, Box<dyn EventHandler>>,
+    events: HashMap<String, Vec<RegisteredHandler>>,
+    raw_handlers: Vec<RegisteredHandler>,
+    strict: bool,
     client: Option<Weak<RefCell<Discord>>>,
 }
 
@@ -33,6 +80,8 @@ impl DefaultEventHandler {
         DefaultEventHandler {
             client: None,
             events: HashMap::new(),
+            raw_handlers: Vec::new(),
+            strict: false,
         }
     }
 
@@ -41,12 +90,104 @@ impl DefaultEventHandler {
         self.client = Some(client);
     }
 
-    /// Registers an event handler for the given event
+    /// Removes a registered handler, returning if it was registered.
+    fn unregister(&mut self, id: HandlerId) -> bool {
+        let handlers = std::iter::once(&mut self.raw_handlers).chain(self.events.values_mut());
+        for handlers in handlers {
+            if let Some(index) = handlers.iter().position(|h| h.id == id) {
+                handlers.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Applies the handler changes that were made through the client.
     ///
-    /// The handler will run whenever that event is received, and will
-    /// replace any previous handler for that event.
-    pub(crate) fn register_event(&mut self, event: String, handler: Box<dyn EventHandler>) {
-        self.events.insert(event, handler);
+    /// Handlers are registered through the client instead of directly, so
+    /// that they can be registered while an event is being handled.
+    fn apply_changes(&mut self, client: &RefCell<Discord>) {
+        let client = client.borrow();
+        let changes: Vec<HandlerChange> = client.handler_changes.borrow_mut().drain(..).collect();
+        // The changes from `DiscordHandle` go last, as they include every
+        // unregistration.
+        let shared_changes: Vec<HandlerChange<dyn EventHandler + Send>> = client
+            .handle
+            .shared
+            .handler_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect();
+        drop(client);
+
+        for change in changes {
+            self.apply_change(change);
+        }
+        for change in shared_changes {
+            self.apply_change(match change {
+                HandlerChange::Register { event, handler } => HandlerChange::Register {
+                    event,
+                    handler: RegisteredHandler {
+                        id: handler.id,
+                        priority: handler.priority,
+                        handler: handler.handler,
+                    },
+                },
+                HandlerChange::Unregister(id) => HandlerChange::Unregister(id),
+            });
+        }
+    }
+
+    fn apply_change(&mut self, change: HandlerChange) {
+        match change {
+            HandlerChange::Register {
+                event: Some(event),
+                handler,
+            } => insert_handler(self.events.entry(event).or_default(), handler),
+            HandlerChange::Register {
+                event: None,
+                handler,
+            } => insert_handler(&mut self.raw_handlers, handler),
+            HandlerChange::Unregister(id) => {
+                if !self.unregister(id) {
+                    log::debug!("Tried to unregister unknown handler {:?}", id);
+                }
+            }
+        }
+    }
+
+    /// Sends the event to every stream from `Discord::event_stream`, and
+    /// forgets the streams that were dropped.
+    fn send_to_streams(&self, client: &RefCell<Discord>, event: &str, data: &serde_json::Value) {
+        let client = client.borrow();
+        let mut streams = client
+            .handle
+            .shared
+            .streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if streams.is_empty() {
+            return;
+        }
+
+        let parsed = match Event::parse(event, data.clone()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::error!("Could not parse {} for event streams: {}", event, e);
+                return;
+            }
+        };
+
+        // Waiting for a stream would hold up everything else, so a stream
+        // that is full loses the event instead.
+        let mut open = Vec::with_capacity(streams.len());
+        for mut stream in streams.drain(..) {
+            if stream.send(&parsed) {
+                open.push(stream);
+            }
+        }
+        *streams = open;
     }
 
     /// A handler function that runs before any of the registered event handlers
@@ -61,9 +202,24 @@ impl DefaultEventHandler {
 
         let c = c.borrow();
 
-        (*c.user.borrow_mut()) = Some(serde_json::from_value(data["user"].clone())?);
+        // Parsed before taking the locks, so they are held as briefly as possible
+        let user = serde_json::from_value(data["user"].clone())?;
+        *c.handle
+            .shared
+            .user
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(user);
 
-        (*c.guilds.borrow_mut()) = serde_json::from_value(data["guilds"].clone())?;
+        // Every shard sends its own READY, with only the guilds of that shard.
+        let new_guilds: Vec<api::guild::Guild> = serde_json::from_value(data["guilds"].clone())?;
+        let mut guilds = c
+            .handle
+            .shared
+            .guilds
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        guilds.retain(|g| !new_guilds.iter().any(|n| n.id == g.id));
+        guilds.extend(new_guilds);
 
         Ok(())
     }
@@ -82,38 +238,358 @@ impl DefaultEventHandler {
         let new_guild: api::guild::Guild = serde_json::from_value(data.clone())?;
 
         // There might be a better method, not sure though.
-        (*c.guilds.borrow_mut()).retain(|g| g.id != new_guild.id);
-        (*c.guilds.borrow_mut()).push(new_guild);
+        let mut guilds = c
+            .handle
+            .shared
+            .guilds
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        guilds.retain(|g| g.id != new_guild.id);
+        guilds.push(new_guild);
         Ok(())
     }
-}
 
-#[async_trait(?Send)]
-impl GatewayEventHandler for DefaultEventHandler {
-    async fn handle(&mut self, event: String, data: serde_json::Value) -> Result<()> {
-        // Special case events
-        match event.as_str() {
-            "READY" => self.pre_ready(&data)?,
-            "GUILD_CREATE" => self.pre_guild_create(&data)?,
-            _ => {}
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "USER_UPDATE" event is received.
+    fn pre_user_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let c = self
+            .client
+            .as_mut()
+            .unwrap()
+            .upgrade()
+            .expect("Cannot upgrade weak client ref pre user update");
+
+        let c = c.borrow();
+        let user = serde_json::from_value(data.clone())?;
+        *c.handle
+            .shared
+            .user
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(user);
+        Ok(())
+    }
+
+    /// Runs `update` on the cached guild with the given id, if there is one.
+    fn update_guild(&mut self, id: api::id::GuildId, update: impl FnOnce(&mut api::guild::Guild)) {
+        let c = self
+            .client
+            .as_mut()
+            .unwrap()
+            .upgrade()
+            .expect("Cannot upgrade weak client ref on guild update");
+
+        let c = c.borrow();
+        let mut guilds = c
+            .handle
+            .shared
+            .guilds
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(guild) = guilds.iter_mut().find(|g| g.id == id) {
+            update(guild);
         }
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_MEMBER_ADD" event is received.
+    fn pre_guild_member_add(&mut self, data: &serde_json::Value) -> Result<()> {
+        let new: api::guild::NewGuildMember = serde_json::from_value(data.clone())?;
+
+        self.update_guild(new.guild_id, |guild| {
+            let members = guild.members.get_or_insert_with(Vec::new);
+            let cached = members.len();
+            members.retain(|m| m.user.id != new.member.user.id);
+            // A member that was already cached, like after a resume, is not new
+            if members.len() == cached {
+                guild.member_count = guild.member_count.map(|count| count + 1);
+            }
+            members.push(new.member);
+        });
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_MEMBER_UPDATE" event is received.
+    fn pre_guild_member_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let updated: api::guild::UpdatedGuildMember = serde_json::from_value(data.clone())?;
+
+        self.update_guild(updated.guild_id, |guild| {
+            let member = guild
+                .members
+                .iter_mut()
+                .flatten()
+                .find(|m| m.user.id == updated.user.id);
+            if let Some(member) = member {
+                member.user = updated.user;
+                member.roles = updated.roles;
+                member.nick = updated.nick;
+                member.premium_since = updated.premium_since;
+            }
+        });
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_MEMBER_REMOVE" event is received.
+    fn pre_guild_member_remove(&mut self, data: &serde_json::Value) -> Result<()> {
+        let removed: api::guild::RemovedGuildMember = serde_json::from_value(data.clone())?;
+
+        self.update_guild(removed.guild_id, |guild| {
+            guild.member_count = guild.member_count.map(|count| count.saturating_sub(1));
+            if let Some(members) = guild.members.as_mut() {
+                members.retain(|m| m.user.id != removed.user.id);
+            }
+        });
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_MEMBERS_CHUNK" event is received.
+    fn pre_guild_members_chunk(&mut self, data: &serde_json::Value) -> Result<()> {
+        let chunk: api::guild::MemberChunk = serde_json::from_value(data.clone())?;
+
+        self.update_guild(chunk.guild_id, |guild| {
+            let members = guild.members.get_or_insert_with(Vec::new);
+            members.retain(|m| !chunk.members.iter().any(|n| n.user.id == m.user.id));
+            members.extend(chunk.members);
+        });
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_UPDATE" event is received.
+    fn pre_guild_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let mut updated: api::guild::Guild = serde_json::from_value(data.clone())?;
+
+        self.update_guild(updated.id, |guild| {
+            // These are only sent with GUILD_CREATE
+            updated.joined = guild.joined.take();
+            updated.large = guild.large;
+            updated.member_count = guild.member_count;
+            updated.members = guild.members.take();
+            updated.channels = guild.channels.take();
+            updated.presences = guild.presences.take();
+            updated.voice_states = guild.voice_states.take();
+            *guild = updated;
+        });
+        Ok(())
+    }
 
-        if let Some(handler) = self.events.get_mut(&event) {
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_DELETE" event is received.
+    fn pre_guild_delete(&mut self, data: &serde_json::Value) -> Result<()> {
+        let deleted: api::guild::UnavailableGuild = serde_json::from_value(data.clone())?;
+
+        if deleted.unavailable {
+            // It comes back with a GUILD_CREATE once the outage is over
+            self.update_guild(deleted.id, |guild| guild.unavailable = Some(true));
+        } else {
             let c = self
                 .client
                 .as_mut()
                 .unwrap()
                 .upgrade()
-                .expect("Cannot upgrade weak client ref on event handle");
+                .expect("Cannot upgrade weak client ref pre guild delete");
 
-            match handler.handle(c.borrow(), event, data).await {
-                Ok(_) => {}
-                Err(e) => log::error!("Event returned error: {}", e),
-            }
+            let c = c.borrow();
+            let mut guilds = c
+                .handle
+                .shared
+                .guilds
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            guilds.retain(|g| g.id != deleted.id);
+        }
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "CHANNEL_CREATE" or "CHANNEL_UPDATE" event is received.
+    fn pre_channel_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let channel: api::channel::Channel = serde_json::from_value(data.clone())?;
+
+        // Direct message channels aren't cached
+        if let Some(guild_id) = channel.guild_id {
+            self.update_guild(guild_id, |guild| {
+                let channels = guild.channels.get_or_insert_with(Vec::new);
+                channels.retain(|c| c.id != channel.id);
+                channels.push(channel);
+            });
+        }
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "CHANNEL_DELETE" event is received.
+    fn pre_channel_delete(&mut self, data: &serde_json::Value) -> Result<()> {
+        let channel: api::channel::Channel = serde_json::from_value(data.clone())?;
+
+        if let Some(guild_id) = channel.guild_id {
+            self.update_guild(guild_id, |guild| {
+                if let Some(channels) = guild.channels.as_mut() {
+                    channels.retain(|c| c.id != channel.id);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "CHANNEL_PINS_UPDATE" event is received.
+    fn pre_channel_pins_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let pins: api::channel::ChannelPins = serde_json::from_value(data.clone())?;
+
+        if let Some(guild_id) = pins.guild_id {
+            self.update_guild(guild_id, |guild| {
+                let channel = guild
+                    .channels
+                    .iter_mut()
+                    .flatten()
+                    .find(|c| c.id == pins.channel_id);
+                if let Some(channel) = channel {
+                    channel.last_pin_timestamp = pins.last_pin_timestamp;
+                }
+            });
         }
+        Ok(())
+    }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_ROLE_CREATE" or "GUILD_ROLE_UPDATE" event is received.
+    fn pre_guild_role_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let role: api::guild::GuildRole = serde_json::from_value(data.clone())?;
+
+        self.update_guild(role.guild_id, |guild| {
+            let roles = guild.roles.get_or_insert_with(Vec::new);
+            roles.retain(|r| r.id != role.role.id);
+            roles.push(role.role);
+        });
+        Ok(())
+    }
 
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_ROLE_DELETE" event is received.
+    fn pre_guild_role_delete(&mut self, data: &serde_json::Value) -> Result<()> {
+        let deleted: api::guild::DeletedRole = serde_json::from_value(data.clone())?;
+
+        self.update_guild(deleted.guild_id, |guild| {
+            if let Some(roles) = guild.roles.as_mut() {
+                roles.retain(|r| r.id != deleted.role_id);
+            }
+            // Members can't have a role that doesn't exist anymore
+            for member in guild.members.iter_mut().flatten() {
+                member.roles.retain(|r| *r != deleted.role_id);
+            }
+        });
         Ok(())
     }
+
+    /// A handler function that runs before any of the registered event handlers
+    /// run when a "GUILD_EMOJIS_UPDATE" event is received.
+    fn pre_guild_emojis_update(&mut self, data: &serde_json::Value) -> Result<()> {
+        let emojis: api::guild::GuildEmojis = serde_json::from_value(data.clone())?;
+
+        self.update_guild(emojis.guild_id, |guild| guild.emojis = Some(emojis.emojis));
+        Ok(())
+    }
+
+    /// Runs the handler functions that keep the cache up to date.
+    fn pre_handle(&mut self, event: &str, data: &serde_json::Value) -> Result<()> {
+        match event {
+            "READY" => self.pre_ready(data),
+            "GUILD_CREATE" => self.pre_guild_create(data),
+            "GUILD_MEMBER_ADD" => self.pre_guild_member_add(data),
+            "GUILD_MEMBER_UPDATE" => self.pre_guild_member_update(data),
+            "GUILD_MEMBER_REMOVE" => self.pre_guild_member_remove(data),
+            "GUILD_MEMBERS_CHUNK" => self.pre_guild_members_chunk(data),
+            "GUILD_UPDATE" => self.pre_guild_update(data),
+            "GUILD_DELETE" => self.pre_guild_delete(data),
+            "CHANNEL_CREATE" | "CHANNEL_UPDATE" => self.pre_channel_update(data),
+            "CHANNEL_DELETE" => self.pre_channel_delete(data),
+            "CHANNEL_PINS_UPDATE" => self.pre_channel_pins_update(data),
+            "GUILD_ROLE_CREATE" | "GUILD_ROLE_UPDATE" => self.pre_guild_role_update(data),
+            "GUILD_ROLE_DELETE" => self.pre_guild_role_delete(data),
+            "GUILD_EMOJIS_UPDATE" => self.pre_guild_emojis_update(data),
+            "USER_UPDATE" => self.pre_user_update(data),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl EventDispatcher for DefaultEventHandler {
+    async fn handle(&mut self, shard: ShardInfo, event: String, data: serde_json::Value) {
+        let c = self
+            .client
+            .as_mut()
+            .unwrap()
+            .upgrade()
+            .expect("Cannot upgrade weak client ref on event handle");
+        let handle = c.borrow().handle.clone();
+
+        // The cache might be outdated after an error, but the connection
+        // can keep going.
+        match panic::catch_unwind(AssertUnwindSafe(|| self.pre_handle(&event, &data))) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => handle.report_error(&event, &data, e),
+            Err(payload) => handle.report_error(&event, &data, DiscordError::from_panic(payload)),
+        }
+
+        // RESUMED is only used by the gateway itself
+        if self.strict && event != "RESUMED" && !crate::events::is_modelled(&event) {
+            let error = GatewayError::UnknownEvent {
+                event: event.clone(),
+            };
+            handle.report_error(&event, &data, error.into());
+        }
+
+        self.send_to_streams(&c, &event, &data);
+
+        // Handlers can be registered and unregistered while the event is being
+        // handled, so they are looked up again before each one runs. The ones
+        // registered after this point only run for the next events.
+        self.apply_changes(&c);
+        let ids: Vec<HandlerId> = self
+            .raw_handlers
+            .iter()
+            .chain(self.events.get(&event).into_iter().flatten())
+            .map(|h| h.id)
+            .collect();
+        let stopped = Rc::new(Cell::new(false));
+
+        for id in ids {
+            self.apply_changes(&c);
+            if stopped.get() {
+                break;
+            }
+
+            let handler = self
+                .raw_handlers
+                .iter_mut()
+                .chain(self.events.get_mut(&event).into_iter().flatten())
+                .find(|h| h.id == id);
+            let handler = match handler {
+                Some(handler) => handler,
+                None => continue,
+            };
+
+            let result = AssertUnwindSafe(handler.handler.handle(
+                Client::new(&c, shard, &stopped),
+                event.clone(),
+                data.clone(),
+            ))
+            .catch_unwind()
+            .await;
+
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => handle.report_error(&event, &data, e),
+                Err(payload) => {
+                    handle.report_error(&event, &data, DiscordError::from_panic(payload))
+                }
+            }
+        }
+    }
 }
 
 /// An instance of a discord client.
@@ -121,19 +597,40 @@ impl GatewayEventHandler for DefaultEventHandler {
 /// This is the "main" struct you'll need to connect to Discord.
 pub struct Discord {
     events: Option<DefaultEventHandler>,
-
-    user: RefCell<Option<api::user::User>>,
-    guilds: RefCell<Vec<api::guild::Guild>>,
+    shards: Shards,
+    identify_queue: Arc<dyn IdentifyQueue>,
+    compress: bool,
+    encoding: Encoding,
+    presence: Option<api::gateway::StatusUpdate>,
+    handler_changes: RefCell<Vec<HandlerChange>>,
+    handle: DiscordHandle,
 }
 
 impl Discord {
     /// Creates a new instance of the Discord client
+    ///
+    /// The client will not send any intents while identifying, so it
+    /// will receive every event except for the privileged ones.
     pub fn new() -> Rc<RefCell<Self>> {
+        Self::create(None)
+    }
+
+    /// Creates a new instance of the Discord client, which will only
+    /// receive the events of the given intents.
+    pub fn with_intents(intents: api::intents::GatewayIntents) -> Rc<RefCell<Self>> {
+        Self::create(Some(intents))
+    }
+
+    fn create(intents: Option<api::intents::GatewayIntents>) -> Rc<RefCell<Self>> {
         let slf = Rc::new(RefCell::new(Self {
             events: Some(DefaultEventHandler::new()),
-
-            user: RefCell::new(None),
-            guilds: RefCell::new(Vec::default()),
+            shards: Shards::default(),
+            identify_queue: Arc::new(LocalIdentifyQueue::new()),
+            compress: false,
+            encoding: Encoding::default(),
+            presence: None,
+            handler_changes: RefCell::new(Vec::new()),
+            handle: DiscordHandle::new(intents),
         }));
 
         {
@@ -149,41 +646,348 @@ impl Discord {
 
     /// Register an event handler struct. See `thatcord::events` for a list of event names.
     ///
+    /// Any number of handlers can be registered for an event, and they run
+    /// in the order they were registered. The returned id can be used to
+    /// unregister the handler with `unregister_event`.
+    ///
+    /// This can be used while the bot is running, even from another event
+    /// handler. A handler registered while an event is being handled will
+    /// only run for the events after it. To register handlers from other
+    /// tasks or threads, use `DiscordHandle::register_event`.
+    ///
+    /// The handlers run one at a time, on the task that runs `connect`, so
+    /// a slow handler holds up every event after it. Use `spawn_event` or
+    /// `spawn_on` for handlers that take a while.
+    ///
+    /// **Warning:** A handler struct can only handle one kind of event.
+    ///
+    /// If the client was created with intents that don't include the event,
+    /// a warning is logged, as the handler will never run.
+    pub fn register_event<T: EventHandler + 'static>(&self, event: &str, handler: T) -> HandlerId {
+        self.register_event_with_priority(event, 0, handler)
+    }
+
+    /// Register an event handler struct like `register_event`, but with a
+    /// priority. Handlers with a higher priority run before the ones with
+    /// a lower priority, and `register_event` uses a priority of 0.
+    pub fn register_event_with_priority<T: EventHandler + 'static>(
+        &self,
+        event: &str,
+        priority: i32,
+        handler: T,
+    ) -> HandlerId {
+        self.handle.check_intents(event);
+        self.add_handler(Some(event.to_owned()), priority, Box::new(handler))
+    }
+
+    /// Register a closure as the handler of an event, which runs like the
+    /// handlers from `register_event`.
+    ///
+    /// ```ignore
+    /// client.on::<events::MessageCreate, _>(|ctx, message| async move {
+    ///     println!("Received a message: {}", message.content);
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn on<E, Fut>(&self, handler: impl FnMut(Context, E::Data) -> Fut + 'static) -> HandlerId
+    where
+        E: EventType + 'static,
+        Fut: Future<Output = Result<()>> + 'static,
+    {
+        self.register_event(E::NAME, ClosureHandler::<E, _>::new(handler))
+    }
+
+    /// Register a closure as the handler of an event like `on`, but the
+    /// future it returns runs as a spawned task (`tokio::spawn`), so it
+    /// doesn't hold up the other events while it runs.
+    ///
+    /// The closure gets a `DiscordHandle` instead of a `Context`, which
+    /// can be moved into the future. As the future runs on its own, it
+    /// can't stop the event from propagating, and its errors reach the
+    /// error handler after the other handlers of the event ran.
+    pub fn spawn_on<E, Fut>(
+        &self,
+        handler: impl FnMut(DiscordHandle, E::Data) -> Fut + 'static,
+    ) -> HandlerId
+    where
+        E: EventType + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_event(E::NAME, SpawnedHandler::<E, _>::new(handler))
+    }
+
+    /// Register an event handler struct like `register_event`, but it runs
+    /// as a spawned task (`tokio::spawn`) for every event, like the futures
+    /// from `spawn_on`.
+    ///
+    /// The handler gets the name of the event and its raw data, which can
+    /// be converted with `serde_json::from_value`.
+    pub fn spawn_event<T: SpawnedEventHandler + 'static>(
+        &self,
+        event: &str,
+        handler: T,
+    ) -> HandlerId {
+        self.register_event(event, SpawnedStructHandler::new(handler))
+    }
+
+    /// Register an event handler struct that receives every event, with
+    /// the name of the event and its raw data.
+    ///
+    /// This runs before the handlers registered with `register_event`,
+    /// and can be used for events that don't have a handler trait in
+    /// `thatcord::events` yet.
+    pub fn register_raw_handler<T: EventHandler + 'static>(&self, handler: T) -> HandlerId {
+        self.add_handler(None, 0, Box::new(handler))
+    }
+
+    fn add_handler(
+        &self,
+        event: Option<String>,
+        priority: i32,
+        handler: Box<dyn EventHandler>,
+    ) -> HandlerId {
+        let id = self.handle.next_handler_id();
+        self.handler_changes
+            .borrow_mut()
+            .push(HandlerChange::Register {
+                event,
+                handler: RegisteredHandler {
+                    id,
+                    priority,
+                    handler,
+                },
+            });
+        id
+    }
+
+    /// Unregisters an event handler, so it won't run for any more events.
+    ///
+    /// Like `register_event`, this can be used while the bot is running.
+    pub fn unregister_event(&self, id: HandlerId) {
+        self.handle.unregister_event(id);
+    }
+
+    /// Gets a stream of every event the client receives, see `DiscordHandle::event_stream`.
+    pub fn event_stream(&self, buffer: usize) -> EventStream {
+        self.handle.event_stream(buffer)
+    }
+
+    /// Creates a collector for events of type `E`, see `DiscordHandle::collector`.
+    pub fn collector<E>(&self) -> Collector<E>
+    where
+        E: EventType + 'static,
+        E::Data: Send,
+    {
+        self.handle.collector()
+    }
+
+    /// Waits for the first event of type `E` that matches the predicate,
+    /// see `DiscordHandle::wait_for`.
+    pub fn wait_for<E>(
+        &self,
+        predicate: impl FnMut(&E::Data) -> bool + Send + 'static,
+        timeout: Duration,
+    ) -> impl Future<Output = Option<E::Data>>
+    where
+        E: EventType + 'static,
+        E::Data: Send,
+    {
+        self.handle.wait_for::<E>(predicate, timeout)
+    }
+
+    /// Sets the function that receives the errors that happen while handling
+    /// events, see `DiscordHandle::set_error_handler`.
+    pub fn set_error_handler(
+        &self,
+        handler: impl Fn(&str, &serde_json::Value, DiscordError) + Send + Sync + 'static,
+    ) {
+        self.handle.set_error_handler(handler)
+    }
+
+    /// Enables reporting events that don't have a handler trait in
+    /// `thatcord::events`. Each of them is passed to the error handler as
+    /// a `GatewayError::UnknownEvent`.
+    ///
+    /// **Warning:** You **can not** use this after calling `connect`.
+    pub fn set_strict_events(&mut self, strict: bool) {
+        self.events
+            .as_mut()
+            .expect("Events not initialized, are you sure you didn't call set_strict_events() after calling connect()?")
+            .strict = strict;
+    }
+
+    /// Sets how many shards to connect with. By default, the shard count
+    /// recommended by Discord is used.
+    ///
+    /// **Warning:** You **can not** use this after calling `connect`.
+    pub fn set_shards(&mut self, shards: Shards) {
+        self.shards = shards;
+    }
+
+    /// Enables `zlib-stream` compression for the gateway connections,
+    /// which makes the events a lot smaller at the cost of some CPU time.
+    ///
     /// **Warning:** You **can not** use this after calling `connect`.
-    /// **Another Warning:** You **can not** use the same handler for multiple events.
-    pub fn register_event<T: EventHandler + 'static>(&mut self, event: &str, handler: T) {
-        self.events.as_mut().expect("Events not initialized, are you sure you didn't call register_event() after calling connect()?").register_event(event.to_owned(), Box::new(handler))
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    /// Sets the format the gateway payloads are sent in. By default,
+    /// `Encoding::Json` is used.
+    ///
+    /// **Warning:** You **can not** use this after calling `connect`.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Sets the presence the bot has as soon as it connects.
+    ///
+    /// `since` is the unix time (in milliseconds) of when the bot went idle.
+    /// Fails with `GatewayError::PayloadTooLarge` if the presence is too
+    /// large to send to the gateway.
+    ///
+    /// **Warning:** You **can not** use this after calling `connect`.
+    pub fn set_initial_presence(
+        &mut self,
+        status: api::guild::OnlineStatus,
+        activity: Option<api::guild::Activity>,
+        afk: bool,
+        since: Option<u64>,
+    ) -> Result<()> {
+        let presence = api::gateway::StatusUpdate {
+            since,
+            game: activity,
+            status,
+            afk,
+        };
+        self.encoding.encode_checked(&api::gateway::Payload {
+            op: api::gateway::GatewayOpcode::StatusUpdate as u8,
+            d: serde_json::to_value(&presence)?,
+            s: None,
+            t: None,
+        })?;
+
+        self.presence = Some(presence);
+        Ok(())
+    }
+
+    /// Sets the queue that decides when shards can identify. By default,
+    /// a `LocalIdentifyQueue` is used, which only knows about the shards of
+    /// this client.
+    ///
+    /// **Warning:** You **can not** use this after calling `connect`.
+    pub fn set_identify_queue(&mut self, queue: Arc<dyn IdentifyQueue>) {
+        self.identify_queue = queue;
     }
 
     /// Get information about the current user/bot.
     /// **Warning:** You **can not** use this until you receive a `thatcord::events::ReadyEvent`.
     pub fn get_current_user(&self) -> api::user::User {
-        self.user
-            .borrow()
-            .as_ref()
-            .expect("Cannot get user before connection ready")
-            .clone()
+        self.handle.get_current_user()
+    }
+
+    /// Updates the presence of the bot on every shard, see `DiscordHandle::set_presence`.
+    pub async fn set_presence(
+        &self,
+        status: api::guild::OnlineStatus,
+        activity: Option<api::guild::Activity>,
+        afk: bool,
+        since: Option<u64>,
+    ) -> Result<()> {
+        self.handle.set_presence(status, activity, afk, since).await
+    }
+
+    /// Requests the members of a guild from the gateway, see
+    /// `DiscordHandle::request_guild_members`.
+    pub async fn request_guild_members(
+        &self,
+        guild: api::id::GuildId,
+        query: api::guild::MemberQuery,
+        limit: u32,
+        presences: bool,
+    ) -> Result<api::guild::RequestedMembers> {
+        self.handle
+            .request_guild_members(guild, query, limit, presences)
+            .await
+    }
+
+    /// Gets how many payloads are waiting for the gateway rate limit,
+    /// over every shard.
+    pub fn gateway_queue_depth(&self) -> usize {
+        self.handle.gateway_queue_depth()
+    }
+
+    /// Gets the health of the gateway connection of every shard, see
+    /// `DiscordHandle::shard_status`.
+    pub fn shard_status(&self) -> Vec<ShardStatus> {
+        self.handle.shard_status()
+    }
+
+    /// Gets the latency of the last heartbeat, averaged over the shards.
+    pub fn latency(&self) -> Option<Duration> {
+        self.handle.latency()
+    }
+
+    /// Gets the average latency of the last few heartbeats, averaged
+    /// over the shards.
+    pub fn average_latency(&self) -> Option<Duration> {
+        self.handle.average_latency()
+    }
+
+    /// Gets a `Send + Sync` handle to the client, which can be moved into
+    /// spawned tasks.
+    pub fn handle(&self) -> DiscordHandle {
+        self.handle.clone()
     }
 
     /// This method will connect to Discord, and start everything.
-    /// **Warning:** This method **will not return** until the connection closes.
+    /// **Warning:** This method **will not return** unless the connection fails
+    /// in a way that reconnecting can't fix, like an invalid token.
     pub async fn connect(this: Rc<RefCell<Self>>, token: &str) -> Result<()> {
-        let mut iself = this.borrow_mut();
         let gateway = api::gateway::get_bot_gateway(token).await?;
 
         // Since this method is the last one that should be called in this struct,
         // we can safely move the event handler off of us, and into the gateway.
-        //
-        // https://stackoverflow.com/questions/31307680/how-to-move-one-field-out-of-a-struct-that-implements-drop-trait
-        let take_ownership_of_events = std::mem::replace(&mut iself.events, None);
-        let mut gateway = Gateway::new(
-            &gateway.url,
-            token,
-            take_ownership_of_events.expect("Events not initialized? It should be..."),
-        )
-        .await?;
-
-        std::mem::drop(iself);
-        Ok(gateway.handle().await?)
+        let (events, shards, config) = {
+            let mut iself = this.borrow_mut();
+            let shards = iself.shards.resolve(gateway.shards)?;
+            let events = iself
+                .events
+                .take()
+                .expect("Events not initialized? It should be...");
+            let config = GatewayConfig {
+                url: gateway.url.clone(),
+                token: token.to_owned(),
+                intents: iself.handle.shared.intents,
+                compress: iself.compress,
+                encoding: iself.encoding,
+                presence: iself.presence.clone(),
+                identify_queue: iself.identify_queue.clone(),
+                member_requests: iself.handle.shared.member_requests.clone(),
+                waiters: iself.handle.shared.waiters.clone(),
+            };
+            (events, shards, config)
+        };
+
+        let limit = gateway.session_start_limit;
+        if (limit.remaining as usize) < shards.len() {
+            log::warn!(
+                "Only {} identifies left for {} shards, some shards will wait {} ms to connect",
+                limit.remaining,
+                shards.len(),
+                limit.reset_after
+            );
+        }
+        config.identify_queue.set_limit(limit).await;
+
+        let (manager, connections) = ShardManager::new(config, shards, events);
+        *this
+            .borrow()
+            .handle
+            .shared
+            .connections
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = connections;
+        manager.run().await
     }
 }
@@ -0,0 +1,447 @@
+use super::{HandlerChange, RegisteredHandler};
+use crate::api;
+use crate::errors::*;
+use crate::events::{
+    ClosureHandler, Collector, CollectorState, Context, EventHandler, EventStream, EventType,
+    HandlerId, SpawnedEventHandler, SpawnedHandler, SpawnedStructHandler, StreamSender,
+};
+use crate::gateway::members::MemberRequests;
+use crate::gateway::shard::ShardHandle;
+use crate::gateway::status::ShardStatus;
+use crate::gateway::waiters::EventWaiters;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+type ErrorHandler = Arc<dyn Fn(&str, &serde_json::Value, DiscordError) + Send + Sync>;
+
+/// The parts of the client that are used while the bot is running.
+#[derive(Default)]
+pub(crate) struct Shared {
+    pub(crate) intents: Option<api::intents::GatewayIntents>,
+    pub(super) handler_changes: Mutex<Vec<HandlerChange<dyn EventHandler + Send>>>,
+    next_handler_id: AtomicU64,
+    pub(crate) connections: RwLock<Vec<ShardHandle>>,
+    pub(crate) member_requests: MemberRequests,
+    pub(crate) waiters: EventWaiters,
+    pub(crate) streams: Mutex<Vec<StreamSender>>,
+    pub(crate) error_handler: Mutex<Option<ErrorHandler>>,
+    pub(crate) user: RwLock<Option<api::user::User>>,
+    pub(crate) guilds: RwLock<Vec<api::guild::Guild>>,
+}
+
+/// A handle to the client that is `Send + Sync`, so it can be moved into
+/// spawned tasks and used from other threads. Get one with `Discord::handle`.
+///
+/// It shares the cache, the event handlers and the gateway connections with
+/// the `Discord` struct it came from, and has the methods that are used
+/// while the bot is running.
+#[derive(Clone, Default)]
+pub struct DiscordHandle {
+    pub(crate) shared: Arc<Shared>,
+}
+
+impl DiscordHandle {
+    pub(crate) fn new(intents: Option<api::intents::GatewayIntents>) -> Self {
+        DiscordHandle {
+            shared: Arc::new(Shared {
+                intents,
+                ..Shared::default()
+            }),
+        }
+    }
+
+    /// Register an event handler struct, like `Discord::register_event`.
+    ///
+    /// This can be used from any task or thread, so the handler has to be
+    /// `Send`. It still runs on the task that runs `Discord::connect`, one
+    /// handler at a time like every other handler. Only the handlers from
+    /// `spawn_event` and `spawn_on` run as spawned tasks.
+    pub fn register_event<T: EventHandler + Send + 'static>(
+        &self,
+        event: &str,
+        handler: T,
+    ) -> HandlerId {
+        self.register_event_with_priority(event, 0, handler)
+    }
+
+    /// Register an event handler struct with a priority, like
+    /// `Discord::register_event_with_priority`.
+    pub fn register_event_with_priority<T: EventHandler + Send + 'static>(
+        &self,
+        event: &str,
+        priority: i32,
+        handler: T,
+    ) -> HandlerId {
+        self.check_intents(event);
+        self.add_handler(Some(event.to_owned()), priority, Box::new(handler))
+    }
+
+    /// Register a closure as the handler of an event, like `Discord::on`.
+    ///
+    /// The future of the closure runs on the task that runs
+    /// `Discord::connect`, and holds up the other events until it's done.
+    pub fn on<E, Fut>(
+        &self,
+        handler: impl FnMut(Context, E::Data) -> Fut + Send + 'static,
+    ) -> HandlerId
+    where
+        E: EventType + 'static,
+        Fut: Future<Output = Result<()>> + 'static,
+    {
+        self.register_event(E::NAME, ClosureHandler::<E, _>::new(handler))
+    }
+
+    /// Register a closure as the handler of an event, whose future runs as
+    /// a spawned task, like `Discord::spawn_on`.
+    pub fn spawn_on<E, Fut>(
+        &self,
+        handler: impl FnMut(DiscordHandle, E::Data) -> Fut + Send + 'static,
+    ) -> HandlerId
+    where
+        E: EventType + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_event(E::NAME, SpawnedHandler::<E, _>::new(handler))
+    }
+
+    /// Register an event handler struct that runs as a spawned task, like
+    /// `Discord::spawn_event`.
+    pub fn spawn_event<T: SpawnedEventHandler + 'static>(
+        &self,
+        event: &str,
+        handler: T,
+    ) -> HandlerId {
+        self.register_event(event, SpawnedStructHandler::new(handler))
+    }
+
+    /// Register an event handler struct that receives every event, like
+    /// `Discord::register_raw_handler`.
+    pub fn register_raw_handler<T: EventHandler + Send + 'static>(&self, handler: T) -> HandlerId {
+        self.add_handler(None, 0, Box::new(handler))
+    }
+
+    /// Unregisters an event handler, so it won't run for any more events.
+    ///
+    /// This works for handlers registered through `Discord` too.
+    pub fn unregister_event(&self, id: HandlerId) {
+        self.shared
+            .handler_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(HandlerChange::Unregister(id));
+    }
+
+    fn add_handler(
+        &self,
+        event: Option<String>,
+        priority: i32,
+        handler: Box<dyn EventHandler + Send>,
+    ) -> HandlerId {
+        let id = self.next_handler_id();
+        self.shared
+            .handler_changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(HandlerChange::Register {
+                event,
+                handler: RegisteredHandler {
+                    id,
+                    priority,
+                    handler,
+                },
+            });
+        id
+    }
+
+    pub(super) fn next_handler_id(&self) -> HandlerId {
+        HandlerId(self.shared.next_handler_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Warns if the client was created with intents that don't include the
+    /// event, as its handlers will never run.
+    pub(super) fn check_intents(&self, event: &str) {
+        if let (Some(intents), Some(required)) = (
+            self.shared.intents,
+            api::intents::GatewayIntents::for_event(event),
+        ) {
+            if !intents.intersects(required) {
+                log::warn!(
+                    "Registered a handler for {}, but none of its intents ({:?}) are enabled",
+                    event,
+                    required
+                );
+            }
+        }
+    }
+
+    /// Get information about the current user/bot.
+    /// **Warning:** You **can not** use this until you receive a `thatcord::events::ReadyEvent`.
+    pub fn get_current_user(&self) -> api::user::User {
+        self.shared
+            .user
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .expect("Cannot get user before connection ready")
+            .clone()
+    }
+
+    /// Updates the presence of the bot on every shard.
+    ///
+    /// `since` is the unix time (in milliseconds) of when the bot went idle.
+    /// If the bot is sending too many commands, the update is queued until
+    /// the gateway rate limit allows it.
+    ///
+    /// https://discordapp.com/developers/docs/topics/gateway#update-status
+    pub async fn set_presence(
+        &self,
+        status: api::guild::OnlineStatus,
+        activity: Option<api::guild::Activity>,
+        afk: bool,
+        since: Option<u64>,
+    ) -> Result<()> {
+        let presence = serde_json::to_value(api::gateway::StatusUpdate {
+            since,
+            game: activity,
+            status,
+            afk,
+        })?;
+
+        let connections = self
+            .shared
+            .connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        for connection in connections.iter() {
+            connection.send(api::gateway::GatewayOpcode::StatusUpdate, presence.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Requests the members of a guild from the gateway, and waits until
+    /// Discord sent all of them.
+    ///
+    /// `limit` is the most members to send, 0 means no limit when requesting
+    /// every member with an empty query. Requesting members needs the
+    /// `GUILD_MEMBERS` intent, and `presences` needs `GUILD_PRESENCES`.
+    ///
+    /// If the connection drops before every member arrives, this never
+    /// completes, so consider wrapping it in `tokio::time::timeout`.
+    ///
+    /// https://discordapp.com/developers/docs/topics/gateway#request-guild-members
+    pub async fn request_guild_members(
+        &self,
+        guild: api::id::GuildId,
+        query: api::guild::MemberQuery,
+        limit: u32,
+        presences: bool,
+    ) -> Result<api::guild::RequestedMembers> {
+        let connection = self
+            .shared
+            .connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .find(|c| c.info.has_guild(guild))
+            .cloned()
+            .ok_or(GatewayError::NoShardForGuild { guild })?;
+
+        let (nonce, members) = self.shared.member_requests.register();
+        let mut request = serde_json::json!({
+            "guild_id": guild,
+            "limit": limit,
+            "presences": presences,
+            "nonce": nonce,
+        });
+        match query {
+            api::guild::MemberQuery::Query(query) => request["query"] = query.into(),
+            api::guild::MemberQuery::UserIds(ids) => {
+                request["user_ids"] = serde_json::to_value(ids)?
+            }
+        }
+
+        connection.send(api::gateway::GatewayOpcode::RequestGuildMembers, request)?;
+        members.await.map_err(|_| GatewayError::ConnectError.into())
+    }
+
+    /// Gets how many payloads are waiting for the gateway rate limit,
+    /// over every shard.
+    pub fn gateway_queue_depth(&self) -> usize {
+        self.shard_status().iter().map(|s| s.queue_depth).sum()
+    }
+
+    /// Gets the health of the gateway connection of every shard, like the
+    /// connection state, heartbeat latency and how often it reconnected.
+    ///
+    /// This is empty until `connect` is called.
+    pub fn shard_status(&self) -> Vec<ShardStatus> {
+        self.shared
+            .connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(ShardHandle::status)
+            .collect()
+    }
+
+    /// Gets the latency of the last heartbeat, averaged over the shards.
+    pub fn latency(&self) -> Option<Duration> {
+        average(self.shard_status().iter().filter_map(|s| s.latency))
+    }
+
+    /// Gets the average latency of the last few heartbeats, averaged
+    /// over the shards.
+    pub fn average_latency(&self) -> Option<Duration> {
+        average(self.shard_status().iter().filter_map(|s| s.average_latency))
+    }
+
+    /// Gets a stream of every event the client receives, which can be used
+    /// instead of, or along with event handlers.
+    ///
+    /// Each stream has its own buffer, which holds up to `buffer` events.
+    /// Nothing waits for a stream that falls behind: the events that don't
+    /// fit in its buffer are lost, and the stream gets an `Event::Lagged`
+    /// with how many it lost.
+    pub fn event_stream(&self, buffer: usize) -> EventStream {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        self.shared
+            .streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(StreamSender::new(sender));
+        EventStream::new(receiver)
+    }
+
+    /// Creates a collector for events of type `E`, which gathers them until
+    /// one of its limits is reached. See `Collector` for the limits.
+    ///
+    /// The collector receives events as soon as they arrive, so it can be
+    /// used from an event handler, before the other handlers of the event
+    /// run.
+    ///
+    /// ```ignore
+    /// let reactions = client
+    ///     .collector::<events::MessageReactionAdd>()
+    ///     .filter(move |reaction| reaction.message_id == message_id)
+    ///     .count(10)
+    ///     .timeout(Duration::from_secs(60))
+    ///     .collect()
+    ///     .await;
+    /// ```
+    pub fn collector<E>(&self) -> Collector<E>
+    where
+        E: EventType + 'static,
+        E::Data: Send,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let state = Arc::new(CollectorState::new());
+        let waiter_state = state.clone();
+        self.shared.waiters.register(move |event, data| {
+            if waiter_state.is_dropped() {
+                return false;
+            }
+            if event != E::NAME {
+                return true;
+            }
+            match E::parse(data.clone()) {
+                Ok(data) if !waiter_state.matches(&data) => true,
+                // Sending only fails once the collector is done
+                Ok(data) => sender.send(data).is_ok(),
+                Err(e) => {
+                    log::error!("Could not parse {} for a collector: {}", event, e);
+                    true
+                }
+            }
+        });
+        Collector::new(receiver, state)
+    }
+
+    /// Waits for the first event of type `E` that matches the predicate.
+    ///
+    /// Returns `None` if no matching event arrived before the timeout.
+    /// Like `collector`, this can be used from an event handler, and it
+    /// starts waiting when it is called, not when it is awaited.
+    pub fn wait_for<E>(
+        &self,
+        predicate: impl FnMut(&E::Data) -> bool + Send + 'static,
+        timeout: Duration,
+    ) -> impl Future<Output = Option<E::Data>>
+    where
+        E: EventType + 'static,
+        E::Data: Send,
+    {
+        let collect = self
+            .collector::<E>()
+            .filter(predicate)
+            .count(1)
+            .timeout(timeout)
+            .collect();
+        async move { collect.await.pop() }
+    }
+
+    /// Sets the function that receives the errors that happen while handling
+    /// events, with the name and raw data of the event. Without one, the
+    /// errors are logged.
+    ///
+    /// This gets the errors returned by event handlers, the panics of event
+    /// handlers as `DiscordError::HandlerPanic`, and the errors that happen
+    /// while updating the cache. None of them stop the client.
+    ///
+    /// The error handler can be called from several threads at once, when
+    /// handlers from `Discord::spawn_on` fail. A panic in the error handler
+    /// is logged.
+    pub fn set_error_handler(
+        &self,
+        handler: impl Fn(&str, &serde_json::Value, DiscordError) + Send + Sync + 'static,
+    ) {
+        *self
+            .shared
+            .error_handler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(handler));
+    }
+
+    /// Passes an error that happened while handling an event to the error
+    /// handler, or logs it if there is none.
+    pub(crate) fn report_error(&self, event: &str, data: &serde_json::Value, error: DiscordError) {
+        // Not holding the lock while it runs lets the error handler replace itself
+        let error_handler = self
+            .shared
+            .error_handler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let error_handler = match error_handler {
+            Some(error_handler) => error_handler,
+            None => {
+                log::error!("Error while handling {}: {}", event, error);
+                return;
+            }
+        };
+
+        if let Err(payload) =
+            panic::catch_unwind(AssertUnwindSafe(|| error_handler(event, data, error)))
+        {
+            log::error!(
+                "Error handler panicked while handling {}: {}",
+                event,
+                DiscordError::from_panic(payload)
+            );
+        }
+    }
+}
+
+fn average(durations: impl Iterator<Item = Duration>) -> Option<Duration> {
+    let (total, count) = durations.fold((Duration::default(), 0), |(total, count), d| {
+        (total + d, count + 1)
+    });
+    if count == 0 {
+        None
+    } else {
+        Some(total / count)
+    }
+}
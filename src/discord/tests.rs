@@ -0,0 +1,753 @@
+use super::*;
+use crate::api::guild::MemberQuery;
+use crate::api::id::{GuildId, Id, UserId};
+use serde_json::json;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+const SHARD: ShardInfo = ShardInfo { id: 0, total: 1 };
+
+fn member(id: &str, nick: Option<&str>) -> serde_json::Value {
+    json!({
+        "user": {"id": id, "username": "member", "discriminator": "0001", "avatar": null},
+        "nick": nick,
+        "roles": [],
+        "joined_at": "2015-04-26T06:26:56.936000+00:00",
+        "deaf": false,
+        "mute": false
+    })
+}
+
+/// Creates a client with a single cached guild, and takes its event handler
+fn client_with_guild(guild: serde_json::Value) -> (Rc<RefCell<Discord>>, DefaultEventHandler) {
+    let client = Discord::new();
+    let events = client.borrow_mut().events.take().unwrap();
+    client
+        .borrow()
+        .handle
+        .shared
+        .guilds
+        .write()
+        .unwrap()
+        .push(serde_json::from_value(guild).unwrap());
+    (client, events)
+}
+
+#[tokio::test]
+async fn test_guild_member_events() {
+    let (client, mut events) = client_with_guild(json!({
+        "id": "1",
+        "member_count": 1,
+        "members": [member("10", None)]
+    }));
+
+    let mut added = member("11", None);
+    added["guild_id"] = json!("1");
+    // Adding the same member twice only counts them once
+    for _ in 0..2 {
+        events
+            .handle(SHARD, "GUILD_MEMBER_ADD".to_owned(), added.clone())
+            .await;
+    }
+
+    let mut updated = member("10", Some("nick"));
+    updated["guild_id"] = json!("1");
+    events
+        .handle(SHARD, "GUILD_MEMBER_UPDATE".to_owned(), updated)
+        .await;
+
+    events
+        .handle(
+            SHARD,
+            "GUILD_MEMBER_REMOVE".to_owned(),
+            json!({"guild_id": "1", "user": member("11", None)["user"]}),
+        )
+        .await;
+
+    events
+        .handle(
+            SHARD,
+            "GUILD_MEMBERS_CHUNK".to_owned(),
+            json!({
+                "guild_id": "1",
+                "members": [member("12", None)],
+                "chunk_index": 0,
+                "chunk_count": 1
+            }),
+        )
+        .await;
+
+    let c = client.borrow();
+    let guilds = c.handle.shared.guilds.read().unwrap();
+    let members = guilds[0].members.as_ref().unwrap();
+    assert_eq!(guilds[0].member_count, Some(1));
+    assert_eq!(members.len(), 2);
+    assert_eq!(members[0].nick.as_deref(), Some("nick"));
+}
+
+#[tokio::test]
+async fn test_guild_state_events() {
+    let (client, mut events) = client_with_guild(json!({
+        "id": "1",
+        "name": "old",
+        "member_count": 1,
+        "members": [member("10", None)],
+        "roles": [],
+        "channels": []
+    }));
+    client
+        .borrow()
+        .handle
+        .shared
+        .guilds
+        .write()
+        .unwrap()
+        .push(serde_json::from_value(json!({"id": "2"})).unwrap());
+
+    let dispatch = vec![
+        ("GUILD_UPDATE", json!({"id": "1", "name": "new"})),
+        (
+            "CHANNEL_CREATE",
+            json!({"id": "20", "type": 0, "guild_id": "1", "name": "general"}),
+        ),
+        (
+            "CHANNEL_CREATE",
+            json!({"id": "21", "type": 0, "guild_id": "1", "name": "memes"}),
+        ),
+        (
+            "CHANNEL_UPDATE",
+            json!({"id": "20", "type": 0, "guild_id": "1", "name": "chat"}),
+        ),
+        (
+            "CHANNEL_DELETE",
+            json!({"id": "21", "type": 0, "guild_id": "1", "name": "memes"}),
+        ),
+        (
+            "CHANNEL_PINS_UPDATE",
+            json!({"guild_id": "1", "channel_id": "20", "last_pin_timestamp": "2020-01-01T00:00:00+00:00"}),
+        ),
+        // Direct messages have no guild and no permission overwrites
+        (
+            "CHANNEL_CREATE",
+            json!({"id": "22", "type": 1, "recipients": []}),
+        ),
+        (
+            "GUILD_ROLE_CREATE",
+            json!({"guild_id": "1", "role": {
+                "id": "30", "name": "mods", "color": 0, "hoist": false, "position": 1,
+                "permissions": 0, "managed": false, "mentionable": false
+            }}),
+        ),
+        (
+            "GUILD_ROLE_CREATE",
+            json!({"guild_id": "1", "role": {
+                "id": "31", "name": "admins", "color": 0, "hoist": false, "position": 2,
+                "permissions": 8, "managed": false, "mentionable": false
+            }}),
+        ),
+        (
+            "GUILD_ROLE_DELETE",
+            json!({"guild_id": "1", "role_id": "30"}),
+        ),
+        (
+            "GUILD_EMOJIS_UPDATE",
+            json!({"guild_id": "1", "emojis": [{"id": "40", "name": "LUL"}]}),
+        ),
+        ("GUILD_DELETE", json!({"id": "1", "unavailable": true})),
+        ("GUILD_DELETE", json!({"id": "2"})),
+    ];
+    for (event, data) in dispatch {
+        events.handle(SHARD, event.to_owned(), data).await;
+    }
+
+    let c = client.borrow();
+    let guilds = c.handle.shared.guilds.read().unwrap();
+    assert_eq!(guilds.len(), 1);
+
+    let guild = &guilds[0];
+    assert_eq!(guild.name.as_deref(), Some("new"));
+    assert_eq!(guild.unavailable, Some(true));
+    assert_eq!(guild.member_count, Some(1));
+    assert_eq!(guild.members.as_ref().unwrap().len(), 1);
+
+    let channels = guild.channels.as_ref().unwrap();
+    assert_eq!(channels.len(), 1);
+    assert_eq!(channels[0].name.as_deref(), Some("chat"));
+    assert!(channels[0].last_pin_timestamp.is_some());
+
+    let roles = guild.roles.as_ref().unwrap();
+    assert_eq!(roles.len(), 1);
+    assert_eq!(roles[0].name, "admins");
+    assert_eq!(guild.emojis.as_ref().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_user_update() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let user =
+        |name: &str| json!({"id": "10", "username": name, "discriminator": "0001", "avatar": null});
+
+    events
+        .handle(
+            SHARD,
+            "READY".to_owned(),
+            json!({"user": user("before"), "guilds": []}),
+        )
+        .await;
+    assert_eq!(client.borrow().get_current_user().username, "before");
+
+    events
+        .handle(SHARD, "USER_UPDATE".to_owned(), user("after"))
+        .await;
+    assert_eq!(client.borrow().get_current_user().username, "after");
+}
+
+#[tokio::test]
+async fn test_poisoned_cache() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+
+    // Panic while holding the lock, like a handler that panics mid-update
+    let shared = client.borrow().handle.shared.clone();
+    std::thread::spawn(move || {
+        let _guilds = shared.guilds.write().unwrap();
+        panic!("poisoning the guilds");
+    })
+    .join()
+    .unwrap_err();
+
+    events
+        .handle(SHARD, "GUILD_CREATE".to_owned(), json!({"id": "2"}))
+        .await;
+
+    let c = client.borrow();
+    let guilds = c
+        .handle
+        .shared
+        .guilds
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    assert_eq!(guilds.len(), 2);
+}
+
+/// Remembers the name of every event it receives
+struct RecordEvents(Rc<RefCell<Vec<String>>>);
+
+#[async_trait(?Send)]
+impl EventHandler for RecordEvents {
+    async fn handle(
+        &mut self,
+        _client: Client<'_>,
+        event: String,
+        _data: serde_json::Value,
+    ) -> Result<()> {
+        self.0.borrow_mut().push(event);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_initial_presence_size() {
+    use crate::api::guild::{Activity, ActivityType, OnlineStatus};
+
+    let client = Discord::new();
+    let mut client = client.borrow_mut();
+    let activity = |name: &str| Some(Activity::new(ActivityType::Game, name));
+    assert!(client
+        .set_initial_presence(OnlineStatus::Online, activity("a game"), false, None)
+        .is_ok());
+    let result = client.set_initial_presence(
+        OnlineStatus::Online,
+        activity(&"a".repeat(5000)),
+        false,
+        None,
+    );
+    assert!(matches!(
+        result,
+        Err(DiscordError::GatewayError(
+            GatewayError::PayloadTooLarge { .. }
+        ))
+    ));
+
+    // The presence that is too large is not used
+    let presence = client.presence.as_ref().unwrap();
+    assert_eq!(presence.game.as_ref().unwrap().name, "a game");
+}
+
+#[tokio::test]
+async fn test_raw_handler() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let raw = Rc::new(RefCell::new(Vec::new()));
+    let typed = Rc::new(RefCell::new(Vec::new()));
+    client
+        .borrow()
+        .register_raw_handler(RecordEvents(raw.clone()));
+    client
+        .borrow()
+        .register_event("TYPING_START", RecordEvents(typed.clone()));
+    events.strict = true;
+
+    events
+        .handle(SHARD, "TYPING_START".to_owned(), json!({}))
+        .await;
+    events
+        .handle(SHARD, "VOICE_SERVER_UPDATE".to_owned(), json!({}))
+        .await;
+
+    assert_eq!(*raw.borrow(), vec!["TYPING_START", "VOICE_SERVER_UPDATE"]);
+    assert_eq!(*typed.borrow(), vec!["TYPING_START"]);
+    assert!(crate::events::is_modelled("TYPING_START"));
+    assert!(!crate::events::is_modelled("VOICE_SERVER_UPDATE"));
+}
+
+/// Remembers its name when it runs, and can stop the event from propagating
+struct Named {
+    name: &'static str,
+    stop: bool,
+    log: Rc<RefCell<Vec<&'static str>>>,
+}
+
+#[async_trait(?Send)]
+impl EventHandler for Named {
+    async fn handle(
+        &mut self,
+        client: Client<'_>,
+        _event: String,
+        _data: serde_json::Value,
+    ) -> Result<()> {
+        self.log.borrow_mut().push(self.name);
+        if self.stop {
+            client.stop_propagation();
+        }
+        Ok(())
+    }
+}
+
+/// Sends a TYPING_START event, and returns the handlers that ran
+async fn typing_start(
+    events: &mut DefaultEventHandler,
+    log: &RefCell<Vec<&'static str>>,
+) -> Vec<&'static str> {
+    events
+        .handle(SHARD, "TYPING_START".to_owned(), json!({}))
+        .await;
+    log.replace(Vec::new())
+}
+
+#[tokio::test]
+async fn test_handler_order() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let register = |name, priority, stop| {
+        let handler = Named {
+            name,
+            stop,
+            log: log.clone(),
+        };
+        client
+            .borrow()
+            .register_event_with_priority("TYPING_START", priority, handler)
+    };
+
+    let first = register("first", 0, false);
+    register("high", 10, false);
+    register("second", 0, false);
+
+    assert_eq!(
+        typing_start(&mut events, &log).await,
+        vec!["high", "first", "second"]
+    );
+
+    client.borrow().unregister_event(first);
+    assert_eq!(
+        typing_start(&mut events, &log).await,
+        vec!["high", "second"]
+    );
+
+    client.borrow().register_event_with_priority(
+        "TYPING_START",
+        5,
+        Named {
+            name: "stop",
+            stop: true,
+            log: log.clone(),
+        },
+    );
+    assert_eq!(typing_start(&mut events, &log).await, vec!["high", "stop"]);
+}
+
+/// Registers another handler when it runs, like a command waiting for a reply
+struct Installer(Rc<RefCell<Vec<&'static str>>>);
+
+#[async_trait(?Send)]
+impl EventHandler for Installer {
+    async fn handle(
+        &mut self,
+        client: Client<'_>,
+        _event: String,
+        _data: serde_json::Value,
+    ) -> Result<()> {
+        let handler = Named {
+            name: "installed",
+            stop: false,
+            log: self.0.clone(),
+        };
+        client.register_event("TYPING_START", handler);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_register_from_handler() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let installer = client
+        .borrow()
+        .register_event("TYPING_START", Installer(log.clone()));
+
+    // The new handler only runs for the events after the one that added it
+    assert!(typing_start(&mut events, &log).await.is_empty());
+    client.borrow().unregister_event(installer);
+    assert_eq!(typing_start(&mut events, &log).await, vec!["installed"]);
+    assert_eq!(typing_start(&mut events, &log).await, vec!["installed"]);
+}
+
+#[tokio::test]
+async fn test_closure_handler() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let typing = Rc::new(RefCell::new(Vec::new()));
+
+    let received = typing.clone();
+    client
+        .borrow()
+        .on::<crate::events::TypingStart, _>(move |ctx, typing| {
+            received.borrow_mut().push(typing.user_id);
+            async move {
+                ctx.stop_propagation();
+                Ok(())
+            }
+        });
+    let later = Rc::new(RefCell::new(Vec::new()));
+    client
+        .borrow()
+        .register_event("TYPING_START", RecordEvents(later.clone()));
+
+    let data = json!({"channel_id": "2", "user_id": "10", "timestamp": 1588000000});
+    events.handle(SHARD, "TYPING_START".to_owned(), data).await;
+    assert_eq!(*typing.borrow(), vec![UserId(Id(10))]);
+    assert!(later.borrow().is_empty());
+}
+
+#[tokio::test]
+async fn test_event_stream() {
+    use futures_util::stream::StreamExt;
+
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let mut stream = client.borrow().event_stream(3);
+    let mut lagging = client.borrow().event_stream(1);
+    drop(client.borrow().event_stream(1));
+
+    let user = json!({"id": "10", "username": "bot", "discriminator": "0001", "avatar": null});
+    let ready = json!({
+        "v": 6,
+        "user": user,
+        "guilds": [{"id": "1", "unavailable": true}],
+        "session_id": "session",
+        "shard": [0, 1]
+    });
+    let typing = json!({"channel_id": "2", "user_id": "10", "timestamp": 1588000000});
+    let dispatched = vec![
+        ("READY", ready),
+        ("TYPING_START", typing),
+        ("VOICE_SERVER_UPDATE", json!({})),
+    ];
+    for (event, data) in dispatched {
+        events.handle(SHARD, event.to_owned(), data).await;
+    }
+
+    match stream.next().await {
+        Some(Event::Ready(ready)) => {
+            assert_eq!(ready.session_id, "session");
+            assert_eq!(ready.guilds.len(), 1);
+            assert_eq!(ready.shard, Some([0, 1]));
+        }
+        event => panic!("Unexpected event {:?}", event),
+    }
+    match stream.next().await {
+        Some(Event::TypingStart(typing)) => assert_eq!(typing.user_id, UserId(Id(10))),
+        event => panic!("Unexpected event {:?}", event),
+    }
+    match stream.next().await {
+        Some(Event::Other(name, _)) => assert_eq!(name, "VOICE_SERVER_UPDATE"),
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    // The full stream lost the events it couldn't hold, and says so
+    assert!(matches!(lagging.next().await, Some(Event::Ready(_))));
+    events
+        .handle(SHARD, "VOICE_SERVER_UPDATE".to_owned(), json!({}))
+        .await;
+    assert!(matches!(lagging.next().await, Some(Event::Lagged(2))));
+    assert!(matches!(lagging.next().await, Some(Event::Other(_, _))));
+
+    // Only the dropped stream was forgotten
+    assert_eq!(
+        client.borrow().handle.shared.streams.lock().unwrap().len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_wait_for() {
+    use crate::events::TypingStart;
+
+    let client = Discord::new();
+    let feed = || {
+        for user in &["10", "11", "12", "13"] {
+            let typing = json!({"channel_id": "2", "user_id": user, "timestamp": 1588000000});
+            client
+                .borrow()
+                .handle
+                .shared
+                .waiters
+                .handle_event("TYPING_START", &typing);
+        }
+    };
+
+    let found = client.borrow().wait_for::<TypingStart>(
+        |typing| typing.user_id == UserId(Id(11)),
+        Duration::from_secs(10),
+    );
+    feed();
+    assert_eq!(found.await.unwrap().user_id, UserId(Id(11)));
+
+    let collected = client
+        .borrow()
+        .collector::<TypingStart>()
+        .filter(|typing| typing.user_id != UserId(Id(10)))
+        .until(|typing| typing.user_id == UserId(Id(12)))
+        .collect();
+    feed();
+    let users: Vec<UserId> = collected.await.into_iter().map(|t| t.user_id).collect();
+    assert_eq!(users, vec![UserId(Id(11)), UserId(Id(12))]);
+
+    let nothing = client
+        .borrow()
+        .wait_for::<TypingStart>(|_| true, Duration::from_millis(10));
+    assert!(nothing.await.is_none());
+
+    // A filter that panics only stops its own collector
+    let panicking = client
+        .borrow()
+        .collector::<TypingStart>()
+        .filter(|_| panic!("filter"))
+        .count(1)
+        .collect();
+    feed();
+    assert!(panicking.await.is_empty());
+
+    // A collector that timed out is forgotten on the next event of any kind
+    let waiters = client.borrow().handle.shared.waiters.clone();
+    assert_eq!(waiters.len(), 0);
+    let nothing = client
+        .borrow()
+        .wait_for::<TypingStart>(|_| true, Duration::from_millis(10));
+    assert!(nothing.await.is_none());
+    assert_eq!(waiters.len(), 1);
+    waiters.handle_event("GUILD_BAN_ADD", &json!({}));
+    assert_eq!(waiters.len(), 0);
+}
+
+#[tokio::test]
+async fn test_error_handler() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let errors = Arc::new(Mutex::new(Vec::new()));
+
+    let reported = errors.clone();
+    client
+        .borrow()
+        .set_error_handler(move |event, _data, error| {
+            reported
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", event, error));
+        });
+    client
+        .borrow()
+        .on::<crate::events::TypingStart, _>(|_, typing| async move {
+            if typing.user_id == UserId(Id(10)) {
+                panic!("typing");
+            }
+            Err(GatewayError::ConnectError.into())
+        });
+    events.strict = true;
+
+    let typing = |user| json!({"channel_id": "2", "user_id": user, "timestamp": 1588000000});
+    let dispatched = vec![
+        ("TYPING_START", typing("10")),
+        ("TYPING_START", typing("11")),
+        ("READY", json!({})),
+        ("VOICE_SERVER_UPDATE", json!({})),
+    ];
+    for (event, data) in dispatched {
+        events.handle(SHARD, event.to_owned(), data).await;
+    }
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 4);
+    assert_eq!(errors[0], "TYPING_START: Event handler panicked: typing");
+    assert!(errors[1].starts_with("TYPING_START: Gateway error"));
+    assert!(errors[2].starts_with("READY: JSON error"));
+    assert!(errors[3].starts_with("VOICE_SERVER_UPDATE: Gateway error"));
+}
+
+#[tokio::test]
+async fn test_error_handler_panic() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let errors = Arc::new(Mutex::new(Vec::new()));
+
+    // The first error handler replaces itself, and then panics
+    let handle = client.borrow().handle();
+    let reported = errors.clone();
+    client.borrow().set_error_handler(move |_, _, _| {
+        let reported = reported.clone();
+        handle.set_error_handler(move |event, _data, _error| {
+            reported.lock().unwrap().push(event.to_owned());
+        });
+        panic!("error handler");
+    });
+    events.strict = true;
+
+    for event in &["VOICE_SERVER_UPDATE", "VOICE_STATE_UPDATE"] {
+        events.handle(SHARD, (*event).to_owned(), json!({})).await;
+    }
+    assert_eq!(*errors.lock().unwrap(), vec!["VOICE_STATE_UPDATE"]);
+}
+
+#[tokio::test]
+async fn test_spawned_handler() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    fn assert_send<T: Send>(_: &T) {}
+
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let handle = client.borrow().handle();
+    assert_send_sync(&handle);
+    assert_send(&handle.wait_for::<crate::events::TypingStart>(|_| true, Duration::from_secs(1)));
+    assert_send(&handle.request_guild_members(
+        GuildId(Id(1)),
+        MemberQuery::Query(String::new()),
+        0,
+        false,
+    ));
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    client
+        .borrow()
+        .spawn_on::<crate::events::TypingStart, _>(move |handle, typing| {
+            let sender = sender.clone();
+            async move {
+                // A slow handler doesn't keep the next events waiting
+                tokio::time::delay_for(Duration::from_millis(10)).await;
+                let user = handle.get_current_user();
+                sender.send((typing.user_id, user.username)).unwrap();
+                Ok(())
+            }
+        });
+
+    let user = json!({"id": "10", "username": "bot", "discriminator": "0001", "avatar": null});
+    events.handle(SHARD, "USER_UPDATE".to_owned(), user).await;
+    let typing = json!({"channel_id": "2", "user_id": "11", "timestamp": 1588000000});
+    events
+        .handle(SHARD, "TYPING_START".to_owned(), typing)
+        .await;
+    assert!(receiver.try_recv().is_err());
+
+    let (user_id, username) = receiver.recv().await.unwrap();
+    assert_eq!(user_id, UserId(Id(11)));
+    assert_eq!(username, "bot");
+}
+
+/// Sends the user id of every TYPING_START it gets, after a while
+struct SlowTyping(mpsc::UnboundedSender<UserId>);
+
+#[async_trait]
+impl SpawnedEventHandler for SlowTyping {
+    async fn handle(
+        &self,
+        _client: DiscordHandle,
+        _event: String,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        let typing: crate::Typing = serde_json::from_value(data)?;
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        self.0.send(typing.user_id).unwrap();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_spawned_struct_handler() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    client
+        .borrow()
+        .spawn_event("TYPING_START", SlowTyping(sender));
+
+    for user in &["10", "11"] {
+        let typing = json!({"channel_id": "2", "user_id": user, "timestamp": 1588000000});
+        events
+            .handle(SHARD, "TYPING_START".to_owned(), typing)
+            .await;
+    }
+    // Neither event waited for the handler
+    assert!(receiver.try_recv().is_err());
+
+    let mut users = vec![
+        receiver.recv().await.unwrap(),
+        receiver.recv().await.unwrap(),
+    ];
+    users.sort_by_key(|user| (user.0).0);
+    assert_eq!(users, vec![UserId(Id(10)), UserId(Id(11))]);
+}
+
+#[tokio::test]
+async fn test_handle_registration() {
+    let (client, mut events) = client_with_guild(json!({"id": "1"}));
+    let handle = client.borrow().handle();
+    let typing = json!({"channel_id": "2", "user_id": "11", "timestamp": 1588000000});
+
+    // Handlers can be registered from other threads
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let registered = handle.clone();
+    let id = std::thread::spawn(move || {
+        registered.spawn_on::<crate::events::TypingStart, _>(move |_, typing| {
+            sender.send(typing.user_id).unwrap();
+            async { Ok(()) }
+        })
+    })
+    .join()
+    .unwrap();
+
+    events
+        .handle(SHARD, "TYPING_START".to_owned(), typing.clone())
+        .await;
+    assert_eq!(receiver.recv().await, Some(UserId(Id(11))));
+
+    // Handlers from `Discord` can be unregistered through the handle too
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let named = client.borrow().register_event(
+        "TYPING_START",
+        Named {
+            name: "named",
+            stop: false,
+            log: log.clone(),
+        },
+    );
+    handle.unregister_event(id);
+    handle.unregister_event(named);
+
+    events
+        .handle(SHARD, "TYPING_START".to_owned(), typing)
+        .await;
+    assert!(receiver.try_recv().is_err());
+    assert!(log.borrow().is_empty());
+}
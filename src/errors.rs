@@ -16,11 +16,27 @@ pub enum DiscordError {
     WebSocketError(websocket_lite::Error),
     HttpError(surf::Exception),
     IoError(std::io::Error),
+    DecompressError(flate2::DecompressError),
     HeartbeatSeqUpdateError(tokio::sync::watch::error::SendError<Option<u64>>),
     SocketThreadError(tokio::sync::mpsc::error::SendError<crate::api::gateway::Payload>),
     HeartbeatTimeError(tokio::sync::watch::error::SendError<std::time::Instant>),
     ApiError(crate::api::ApiError),
     GatewayError(GatewayError),
+    HandlerPanic(String),
+}
+
+impl DiscordError {
+    /// Converts the payload of a caught panic into an error.
+    pub(crate) fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => (*message).to_owned(),
+                Err(_) => "unknown panic".to_owned(),
+            },
+        };
+        Self::HandlerPanic(message)
+    }
 }
 
 impl std::fmt::Display for DiscordError {
@@ -29,6 +45,7 @@ impl std::fmt::Display for DiscordError {
             Self::JsonError(ref e) => write!(f, "JSON error: {}", e),
             Self::WebSocketError(ref e) => write!(f, "Web Socket error: {}", e),
             Self::IoError(ref e) => write!(f, "IO error: {}", e),
+            Self::DecompressError(ref e) => write!(f, "Decompression error: {}", e),
             Self::HttpError(ref e) => write!(f, "HTTP error: {}", e),
             Self::ApiError(ref e) => write!(f, "Discord API error ({}): {}", e.code, e.message),
             Self::HeartbeatSeqUpdateError(ref e) => {
@@ -39,6 +56,7 @@ impl std::fmt::Display for DiscordError {
                 write!(f, "Socket thread communcation failiure: {}", e)
             }
             Self::GatewayError(ref e) => write!(f, "Gateway error: {}", e),
+            Self::HandlerPanic(ref message) => write!(f, "Event handler panicked: {}", message),
         }
     }
 }
@@ -47,6 +65,7 @@ convert_error!(serde_json::Error, DiscordError, JsonError);
 convert_error!(websocket_lite::Error, DiscordError, WebSocketError);
 convert_error!(crate::api::ApiError, DiscordError, ApiError);
 convert_error!(std::io::Error, DiscordError, IoError);
+convert_error!(flate2::DecompressError, DiscordError, DecompressError);
 convert_error!(
     tokio::sync::watch::error::SendError<Option<u64>>,
     DiscordError,
@@ -67,6 +86,12 @@ pub enum GatewayError {
     UnknownOpcode { opcode: u8 },
     InvalidResponseError { what: String },
     UnknownEvent { event: String },
+    FatalClose { code: u16, reason: String },
+    InvalidShards { what: String },
+    NoShardForGuild { guild: crate::api::id::GuildId },
+    PayloadTooLarge { size: usize },
+    #[cfg(feature = "etf")]
+    EtfError { what: String },
 }
 
 impl std::fmt::Display for GatewayError {
@@ -78,6 +103,21 @@ impl std::fmt::Display for GatewayError {
             Self::UnknownOpcode { opcode } => write!(f, "Unknown opcode: {}", opcode),
             Self::InvalidResponseError { what } => write!(f, "Invalid response: {}", what),
             Self::UnknownEvent { event } => write!(f, "Unknown event: {}", event),
+            Self::FatalClose { code, reason } => {
+                write!(f, "Connection closed by Discord ({}): {}", code, reason)
+            }
+            Self::InvalidShards { what } => write!(f, "Invalid shards: {}", what),
+            Self::NoShardForGuild { guild } => {
+                write!(f, "None of the running shards has guild {}", guild)
+            }
+            Self::PayloadTooLarge { size } => write!(
+                f,
+                "Payload is {} bytes, over the limit of {} bytes",
+                size,
+                crate::gateway::encoding::MAX_PAYLOAD_SIZE
+            ),
+            #[cfg(feature = "etf")]
+            Self::EtfError { what } => write!(f, "Invalid ETF payload: {}", what),
         }
     }
 }
@@ -1,8 +1,465 @@
-use crate::{Discord, Result};
-use std::cell::Ref;
+use crate::gateway::ShardInfo;
+use crate::{Discord, DiscordHandle, Result};
+use futures_util::future::FutureExt;
+use std::cell::{Cell, Ref, RefCell};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::Poll;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
-/// Convenience type alias for the client struct
-pub type Client<'a> = Ref<'a, Discord>;
+/// Identifies a registered event handler, so it can be unregistered later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerId(pub(crate) u64);
+
+/// The client that received an event.
+///
+/// This dereferences to the `Discord` struct, and also knows which
+/// shard the event came from.
+pub struct Client<'a> {
+    client: &'a Rc<RefCell<Discord>>,
+    discord: Ref<'a, Discord>,
+    shard: ShardInfo,
+    stopped: &'a Rc<Cell<bool>>,
+}
+
+impl<'a> Client<'a> {
+    pub(crate) fn new(
+        client: &'a Rc<RefCell<Discord>>,
+        shard: ShardInfo,
+        stopped: &'a Rc<Cell<bool>>,
+    ) -> Self {
+        Client {
+            client,
+            discord: client.borrow(),
+            shard,
+            stopped,
+        }
+    }
+
+    /// The shard that received the event.
+    pub fn shard(&self) -> ShardInfo {
+        self.shard
+    }
+
+    /// Stops the event from reaching the handlers that would run after
+    /// this one.
+    pub fn stop_propagation(&self) {
+        self.stopped.set(true);
+    }
+
+    /// Gets a `Context` for the event, which isn't bound to the lifetime
+    /// of the client.
+    pub fn context(&self) -> Context {
+        Context {
+            client: self.client.clone(),
+            shard: self.shard,
+            stopped: self.stopped.clone(),
+        }
+    }
+}
+
+/// The client that received an event, for the handlers registered with
+/// `Discord::on`.
+///
+/// Unlike `Client`, this can be moved into the future of the handler.
+#[derive(Clone)]
+pub struct Context {
+    client: Rc<RefCell<Discord>>,
+    shard: ShardInfo,
+    stopped: Rc<Cell<bool>>,
+}
+
+impl Context {
+    /// The `Discord` struct of the client.
+    pub fn discord(&self) -> Ref<'_, Discord> {
+        self.client.borrow()
+    }
+
+    /// The shard that received the event.
+    pub fn shard(&self) -> ShardInfo {
+        self.shard
+    }
+
+    /// Stops the event from reaching the handlers that would run after
+    /// this one.
+    pub fn stop_propagation(&self) {
+        self.stopped.set(true);
+    }
+}
+
+/// An event that closures can be registered for with `Discord::on`.
+///
+/// Every event in this module has a type for this, named after the
+/// event, like `MessageCreate` for `MESSAGE_CREATE`.
+pub trait EventType {
+    /// The name of the event
+    const NAME: &'static str;
+
+    /// The data the handlers of the event receive
+    type Data;
+
+    /// Converts the raw event data
+    #[doc(hidden)]
+    fn parse(data: serde_json::Value) -> Result<Self::Data>;
+}
+
+/// Runs a closure registered with `Discord::on` for its event.
+pub(crate) struct ClosureHandler<E, F> {
+    handler: F,
+    event: PhantomData<fn() -> E>,
+}
+
+impl<E, F> ClosureHandler<E, F> {
+    pub(crate) fn new(handler: F) -> Self {
+        ClosureHandler {
+            handler,
+            event: PhantomData,
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<E, F, Fut> EventHandler for ClosureHandler<E, F>
+where
+    E: EventType,
+    F: FnMut(Context, E::Data) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    async fn handle(
+        &mut self,
+        client: Client<'_>,
+        _event: String,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        let data = E::parse(data)?;
+        let context = client.context();
+        drop(client);
+        (self.handler)(context, data).await
+    }
+}
+
+type Condition<T> = Box<dyn FnMut(&T) -> bool + Send>;
+
+/// Runs the future of a closure registered with `Discord::spawn_on` as a
+/// spawned task.
+pub(crate) struct SpawnedHandler<E, F> {
+    handler: F,
+    event: PhantomData<fn() -> E>,
+}
+
+impl<E, F> SpawnedHandler<E, F> {
+    pub(crate) fn new(handler: F) -> Self {
+        SpawnedHandler {
+            handler,
+            event: PhantomData,
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<E, F, Fut> EventHandler for SpawnedHandler<E, F>
+where
+    E: EventType,
+    F: FnMut(DiscordHandle, E::Data) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    async fn handle(
+        &mut self,
+        client: Client<'_>,
+        event: String,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        let handle = client.handle();
+        let future = (self.handler)(handle.clone(), E::parse(data.clone())?);
+        spawn_handler(handle, event, data, future);
+        Ok(())
+    }
+}
+
+/// An event handler struct that runs as a spawned task (`tokio::spawn`),
+/// registered with `Discord::spawn_event`.
+///
+/// Unlike `EventHandler`, it gets a `DiscordHandle` instead of a `Client`.
+/// It can run for several events at once, so it only gets `&self`.
+#[async_trait::async_trait]
+pub trait SpawnedEventHandler: Send + Sync {
+    async fn handle(
+        &self,
+        client: DiscordHandle,
+        event: String,
+        data: serde_json::Value,
+    ) -> Result<()>;
+}
+
+/// Runs a handler registered with `Discord::spawn_event` as a spawned task.
+pub(crate) struct SpawnedStructHandler<T>(Arc<T>);
+
+impl<T> SpawnedStructHandler<T> {
+    pub(crate) fn new(handler: T) -> Self {
+        SpawnedStructHandler(Arc::new(handler))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<T: SpawnedEventHandler + 'static> EventHandler for SpawnedStructHandler<T> {
+    async fn handle(
+        &mut self,
+        client: Client<'_>,
+        event: String,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        let handle = client.handle();
+        let handler = self.0.clone();
+        let future = {
+            let (handle, event, data) = (handle.clone(), event.clone(), data.clone());
+            async move { handler.handle(handle, event, data).await }
+        };
+        spawn_handler(handle, event, data, future);
+        Ok(())
+    }
+}
+
+/// Runs the future of a handler as a spawned task, and passes its error or
+/// panic to the error handler.
+fn spawn_handler(
+    handle: DiscordHandle,
+    event: String,
+    data: serde_json::Value,
+    future: impl Future<Output = Result<()>> + Send + 'static,
+) {
+    tokio::spawn(async move {
+        let error = match AssertUnwindSafe(future).catch_unwind().await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => e,
+            Err(payload) => crate::DiscordError::from_panic(payload),
+        };
+        handle.report_error(&event, &data, error);
+    });
+}
+
+/// What a `Collector` shares with the waiter that sends it events.
+pub(crate) struct CollectorState<T> {
+    filter: Mutex<Option<Condition<T>>>,
+    dropped: AtomicBool,
+}
+
+impl<T> CollectorState<T> {
+    pub(crate) fn new() -> Self {
+        CollectorState {
+            filter: Mutex::new(None),
+            dropped: AtomicBool::new(false),
+        }
+    }
+
+    /// Checks if the event passes the filter of the collector. The filter
+    /// runs before the event is sent, so the events it rejects are never
+    /// buffered.
+    pub(crate) fn matches(&self, data: &T) -> bool {
+        match self
+            .filter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            Some(filter) => filter(data),
+            None => true,
+        }
+    }
+
+    /// Checks if the collector was dropped, so nothing receives the events.
+    pub(crate) fn is_dropped(&self) -> bool {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Gathers the events of type `E`, from `Discord::collector`.
+///
+/// Collecting stops once `count` events were collected, the `timeout`
+/// passed, or an event matched the `until` condition. Without any of
+/// these, it never stops.
+pub struct Collector<E: EventType> {
+    receiver: mpsc::UnboundedReceiver<E::Data>,
+    state: Arc<CollectorState<E::Data>>,
+    until: Option<Condition<E::Data>>,
+    count: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl<E: EventType> Collector<E> {
+    pub(crate) fn new(
+        receiver: mpsc::UnboundedReceiver<E::Data>,
+        state: Arc<CollectorState<E::Data>>,
+    ) -> Self {
+        Collector {
+            receiver,
+            state,
+            until: None,
+            count: None,
+            timeout: None,
+        }
+    }
+
+    /// Only collects the events that match the filter.
+    ///
+    /// The filter runs as the events arrive, before the event handlers.
+    /// If it panics, the collector stops receiving events.
+    pub fn filter(self, filter: impl FnMut(&E::Data) -> bool + Send + 'static) -> Self {
+        *self
+            .state
+            .filter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Box::new(filter));
+        self
+    }
+
+    /// Stops after an event matches the condition. That event is the last
+    /// one that is collected.
+    pub fn until(mut self, until: impl FnMut(&E::Data) -> bool + Send + 'static) -> Self {
+        self.until = Some(Box::new(until));
+        self
+    }
+
+    /// Stops once this many events were collected.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Stops once the timeout passed, counting from when `collect` is first awaited.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Collects events until one of the limits is reached.
+    pub async fn collect(mut self) -> Vec<E::Data> {
+        let deadline = self.timeout.map(|t| tokio::time::Instant::now() + t);
+        let mut collected = Vec::new();
+
+        while Some(collected.len()) != self.count {
+            let next = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, self.receiver.recv()).await {
+                        Ok(next) => next,
+                        Err(_) => break,
+                    }
+                }
+                None => self.receiver.recv().await,
+            };
+            let data = match next {
+                Some(data) => data,
+                None => break,
+            };
+
+            let done = match self.until.as_mut() {
+                Some(until) => until(&data),
+                None => false,
+            };
+            collected.push(data);
+            if done {
+                break;
+            }
+        }
+
+        collected
+    }
+}
+
+impl<E: EventType> Drop for Collector<E> {
+    fn drop(&mut self) {
+        // Lets the waiter know it can be removed
+        self.state.dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A stream of every event the client receives, from `Discord::event_stream`.
+///
+/// Events are buffered until the stream is polled. The client doesn't wait
+/// for a stream that falls behind: if the buffer is full when an event
+/// arrives, the event is lost. The next event that fits is preceded by an
+/// `Event::Lagged` with how many events were lost.
+///
+/// The stream only ends once the client and its handles are dropped.
+pub struct EventStream {
+    receiver: mpsc::Receiver<(u64, Event)>,
+    next: Option<Event>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: mpsc::Receiver<(u64, Event)>) -> Self {
+        EventStream {
+            receiver,
+            next: None,
+        }
+    }
+}
+
+impl futures_util::stream::Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Option<Event>> {
+        if let Some(event) = self.next.take() {
+            return Poll::Ready(Some(event));
+        }
+
+        match self.receiver.poll_recv(cx) {
+            Poll::Ready(Some((lost, event))) if lost > 0 => {
+                self.next = Some(event);
+                Poll::Ready(Some(Event::Lagged(lost)))
+            }
+            Poll::Ready(Some((_, event))) => Poll::Ready(Some(event)),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Sends events to an `EventStream`, along with how many events it lost
+/// since the last one it received.
+pub(crate) struct StreamSender {
+    sender: mpsc::Sender<(u64, Event)>,
+    lost: u64,
+}
+
+impl StreamSender {
+    pub(crate) fn new(sender: mpsc::Sender<(u64, Event)>) -> Self {
+        StreamSender { sender, lost: 0 }
+    }
+
+    /// Sends an event without waiting for room in the buffer.
+    ///
+    /// Returns false once the stream was dropped.
+    pub(crate) fn send(&mut self, event: &Event) -> bool {
+        match self.sender.try_send((self.lost, event.clone())) {
+            Ok(()) => {
+                self.lost = 0;
+                true
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                if self.lost == 0 {
+                    log::warn!("An event stream is not polled fast enough, losing events");
+                }
+                self.lost += 1;
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+impl std::ops::Deref for Client<'_> {
+    type Target = Discord;
+
+    fn deref(&self) -> &Discord {
+        &self.discord
+    }
+}
 
 // Q: Why is every macro parameter a tt?
 // A: https://github.com/dtolnay/async-trait/issues/46
@@ -17,27 +474,52 @@ macro_rules! impl_event {
     }
 }
 
+// An event whose handlers get no data can still give its data to the
+// `Event` enum, with `=> (Type)` after its parameters.
 macro_rules! define_events {
-    ($d:tt $($(#[$meta:meta])* event $name:tt, $gateway_name:tt, ($($param_name:tt: $param_type:tt),*) $map:tt < ($($use_param_name:tt: $use_param_type:tt),*))*) => {
+    ($d:tt $($(#[$meta:meta])* event $name:tt, $gateway_name:tt, ($($param_name:tt: $param_type:tt),*) $(=> $event_type:tt)?;)*) => {
         $(
             $(#[$meta])*
             #[async_trait::async_trait(?Send)]
             #[allow(unused_parens)]
             pub trait $name: EventHandler {
-                async fn handle_real(&mut self $(, $param_name: $param_type)*) -> Result<()>;
+                async fn handle_real(&mut self, client: Client<'_> $(, $param_name: $param_type)*) -> Result<()>;
+
+                /// Converts the raw event data, and passes it to `handle_real`
+                #[doc(hidden)]
+                #[allow(unused_variables)]
+                async fn handle_event(&mut self, client: Client<'_>, data: serde_json::Value) -> Result<()> {
+                    self.handle_real(client $(, serde_json::from_value::<$param_type>(data)?)*).await
+                }
             }
 
             pub const $gateway_name: &str = stringify!($gateway_name);
 
+            paste::item! {
+                $(#[$meta])*
+                pub enum [<$gateway_name:camel>] {}
+
+                #[allow(unused_parens)]
+                impl EventType for [<$gateway_name:camel>] {
+                    const NAME: &'static str = $gateway_name;
+                    type Data = ($($param_type)*);
+
+                    #[allow(unused_variables)]
+                    fn parse(data: serde_json::Value) -> Result<Self::Data> {
+                        Ok(($(serde_json::from_value::<$param_type>(data)?)*))
+                    }
+                }
+            }
+
             paste::item! {
                 #[macro_export]
                 macro_rules! [<_thatcord_internal_impl_ $name>] {
                     ($typ:tt) => {
                         #[async_trait::async_trait(?Send)]
                         impl $crate::events::EventHandler for $typ {
-                            async fn handle(&mut self, $($use_param_name: $use_param_type),*) -> $crate::Result<()> {
+                            async fn handle(&mut self, client: $crate::events::Client<'_>, _event: String, data: serde_json::Value) -> $crate::Result<()> {
                                 use $crate::events::$name;
-                                $map
+                                self.handle_event(client, data).await
                             }
                         }
                     }
@@ -58,6 +540,44 @@ macro_rules! define_events {
                 }
             }
         )*
+
+        paste::item! {
+            /// Every event the client can receive, for `Discord::event_stream`.
+            #[derive(Debug, Clone)]
+            #[allow(unused_parens)]
+            #[non_exhaustive]
+            pub enum Event {
+                $(
+                    $(#[$meta])*
+                    [<$gateway_name:camel>]($($event_type)? $($param_type)*),
+                )*
+                /// An event that doesn't have a variant yet, with its name and raw data
+                Other(String, serde_json::Value),
+                /// The stream fell behind, and lost this many events
+                Lagged(u64),
+            }
+
+            impl Event {
+                /// Converts the raw data of an event, like the handlers of the event do.
+                #[allow(unused_parens, unused_variables)]
+                pub(crate) fn parse(event: &str, data: serde_json::Value) -> Result<Self> {
+                    $(
+                        if event == $gateway_name {
+                            return Ok(Event::[<$gateway_name:camel>](
+                                $(serde_json::from_value::<$event_type>(data)?)?
+                                $(serde_json::from_value::<$param_type>(data)?)*
+                            ));
+                        }
+                    )*
+                    Ok(Event::Other(event.to_owned(), data))
+                }
+            }
+        }
+
+        /// Checks if there is an event handler trait for the event.
+        pub(crate) fn is_modelled(event: &str) -> bool {
+            [$(stringify!($gateway_name)),*].contains(&event)
+        }
     }
 }
 
@@ -74,21 +594,117 @@ pub trait EventHandler {
 
 define_events!($
     /// This event will fire when you've been successfully connected to Discord.
-    event ReadyEvent, READY, (
-        client: (crate::events::Client<'_>)
-    ) {
-        self.handle_real(client).await
-    } < (client: (crate::events::Client<'_>), event: String, data: (serde_json::Value))
+    /// Every shard sends its own READY, and its data is in `Event::Ready`.
+    event ReadyEvent, READY, () => (crate::Ready);
 
     /// This event will fire when you receive a guild creation event.
     /// This will happen due to:
     ///  * Lazy loaded guilds on startup
     ///  * Added to a new guild
     ///  * Guild becomes available after an outage
-    event GuildCreateEvent, GUILD_CREATE, (
-        client: (crate::events::Client<'_>),
-        guild: (crate::Guild)
-    ) {
-        self.handle_real(client, serde_json::from_value(data).unwrap()).await
-    } < (client: (crate::events::Client<'_>), event: String, data: (serde_json::Value))
+    event GuildCreateEvent, GUILD_CREATE, (guild: (crate::Guild));
+
+    /// This event will fire when a message is sent in a channel the bot can see.
+    event MessageCreateEvent, MESSAGE_CREATE, (message: (crate::Message));
+
+    /// This event will fire when a message is edited. Only the changed
+    /// fields of the message are sent.
+    event MessageUpdateEvent, MESSAGE_UPDATE, (message: (crate::PartialMessage));
+
+    /// This event will fire when a message is deleted.
+    event MessageDeleteEvent, MESSAGE_DELETE, (message: (crate::DeletedMessage));
+
+    /// This event will fire when multiple messages are deleted at once,
+    /// for example when a moderator bans a user and deletes their messages.
+    event MessageDeleteBulkEvent, MESSAGE_DELETE_BULK, (messages: (crate::DeletedMessages));
+
+    /// This event will fire when a user reacts to a message.
+    event MessageReactionAddEvent, MESSAGE_REACTION_ADD, (reaction: (crate::MessageReaction));
+
+    /// This event will fire when a user removes their reaction from a message.
+    event MessageReactionRemoveEvent, MESSAGE_REACTION_REMOVE, (reaction: (crate::MessageReaction));
+
+    /// This event will fire when every reaction is removed from a message at once.
+    event MessageReactionRemoveAllEvent, MESSAGE_REACTION_REMOVE_ALL, (reactions: (crate::ClearedReactions));
+
+    /// This event will fire when every reaction with a single emoji is
+    /// removed from a message at once.
+    event MessageReactionRemoveEmojiEvent, MESSAGE_REACTION_REMOVE_EMOJI, (reactions: (crate::ClearedEmojiReactions));
+
+    /// This event will fire when a user joins a guild.
+    /// Needs the `GUILD_MEMBERS` intent.
+    event GuildMemberAddEvent, GUILD_MEMBER_ADD, (member: (crate::NewGuildMember));
+
+    /// This event will fire when a guild member changes, like when their
+    /// nickname or roles change.
+    /// Needs the `GUILD_MEMBERS` intent.
+    event GuildMemberUpdateEvent, GUILD_MEMBER_UPDATE, (member: (crate::UpdatedGuildMember));
+
+    /// This event will fire when a user leaves, or is kicked or banned from a guild.
+    /// Needs the `GUILD_MEMBERS` intent.
+    event GuildMemberRemoveEvent, GUILD_MEMBER_REMOVE, (member: (crate::RemovedGuildMember));
+
+    /// This event will fire for every part of the members requested with
+    /// `Discord::request_guild_members`.
+    event GuildMembersChunkEvent, GUILD_MEMBERS_CHUNK, (chunk: (crate::MemberChunk));
+
+    /// This event will fire when the settings of a guild change.
+    event GuildUpdateEvent, GUILD_UPDATE, (guild: (crate::Guild));
+
+    /// This event will fire when the bot leaves or is removed from a guild,
+    /// or when a guild becomes unavailable because of an outage.
+    event GuildDeleteEvent, GUILD_DELETE, (guild: (crate::UnavailableGuild));
+
+    /// This event will fire when a channel is created.
+    event ChannelCreateEvent, CHANNEL_CREATE, (channel: (crate::Channel));
+
+    /// This event will fire when a channel changes.
+    event ChannelUpdateEvent, CHANNEL_UPDATE, (channel: (crate::Channel));
+
+    /// This event will fire when a channel is deleted.
+    event ChannelDeleteEvent, CHANNEL_DELETE, (channel: (crate::Channel));
+
+    /// This event will fire when a message is pinned or unpinned.
+    event ChannelPinsUpdateEvent, CHANNEL_PINS_UPDATE, (pins: (crate::ChannelPins));
+
+    /// This event will fire when a role is created.
+    event GuildRoleCreateEvent, GUILD_ROLE_CREATE, (role: (crate::GuildRole));
+
+    /// This event will fire when a role changes.
+    event GuildRoleUpdateEvent, GUILD_ROLE_UPDATE, (role: (crate::GuildRole));
+
+    /// This event will fire when a role is deleted.
+    event GuildRoleDeleteEvent, GUILD_ROLE_DELETE, (role: (crate::DeletedRole));
+
+    /// This event will fire when the emojis of a guild change.
+    event GuildEmojisUpdateEvent, GUILD_EMOJIS_UPDATE, (emojis: (crate::GuildEmojis));
+
+    /// This event will fire when a user is banned from a guild.
+    event GuildBanAddEvent, GUILD_BAN_ADD, (ban: (crate::GuildBan));
+
+    /// This event will fire when a user is unbanned from a guild.
+    event GuildBanRemoveEvent, GUILD_BAN_REMOVE, (ban: (crate::GuildBan));
+
+    /// This event will fire when an invite is created.
+    event InviteCreateEvent, INVITE_CREATE, (invite: (crate::CreatedInvite));
+
+    /// This event will fire when an invite is deleted or expires.
+    event InviteDeleteEvent, INVITE_DELETE, (invite: (crate::DeletedInvite));
+
+    /// This event will fire when a webhook of a channel is created, changed or deleted.
+    event WebhooksUpdateEvent, WEBHOOKS_UPDATE, (webhooks: (crate::UpdatedWebhooks));
+
+    /// This event will fire when an integration of a guild is created, changed or deleted.
+    event GuildIntegrationsUpdateEvent, GUILD_INTEGRATIONS_UPDATE, (integrations: (crate::UpdatedIntegrations));
+
+    /// This event will fire when the status, activity or nickname of a guild member changes.
+    /// Needs the `GUILD_PRESENCES` intent.
+    event PresenceUpdateEvent, PRESENCE_UPDATE, (presence: (crate::PresenceUpdate));
+
+    /// This event will fire when a user starts typing in a channel.
+    event TypingStartEvent, TYPING_START, (typing: (crate::Typing));
+
+    /// This event will fire when the bot user itself changes, like its name or avatar.
+    /// `Discord::get_current_user` already returns the new user when this runs.
+    event UserUpdateEvent, USER_UPDATE, (user: (crate::User));
 );
@@ -1,18 +1,70 @@
 use crate::api::gateway;
-use crate::errors::{GatewayError, Result};
+use crate::api::intents::GatewayIntents;
+use crate::errors::{DiscordError, GatewayError, Result};
 use async_trait::async_trait;
+use futures_util::{FutureExt, StreamExt};
+use rand::Rng;
 use serde_json::json;
+use std::sync::Arc;
 use websocket_lite::ClientBuilder;
 
+pub(crate) mod encoding;
+#[cfg(feature = "etf")]
+mod etf;
 mod heartbeat;
+pub(crate) mod identify;
+mod inflate;
+pub(crate) mod members;
+mod ratelimit;
+mod reconnect;
+pub(crate) mod shard;
 mod socket;
+pub(crate) mod status;
+pub(crate) mod waiters;
 
+#[cfg(test)]
+mod tests;
+
+use encoding::Encoding;
 use heartbeat::{heartbeat, HeartbeatHandler, HeartbeatSender};
-use socket::{Client, Connection};
+pub(crate) use identify::IdentifyQueue;
+use reconnect::{Backoff, Handshake};
+pub(crate) use shard::{ShardInfo, ShardManager};
+use socket::{Client, CloseFrame, Connection};
+use status::{ConnectionState, ShardMetrics};
+use std::sync::atomic::Ordering;
 
 #[async_trait(?Send)]
 pub(crate) trait EventHandler {
-    async fn handle(&mut self, event: String, data: serde_json::Value) -> Result<()>;
+    async fn handle(
+        &mut self,
+        shard: ShardInfo,
+        event: String,
+        data: serde_json::Value,
+    ) -> Result<()>;
+}
+
+/// Handles the events of every shard, which the `ShardManager` collected.
+///
+/// It can't fail, so that an event that can't be handled doesn't stop
+/// the shards. Errors have to be reported by the dispatcher itself.
+#[async_trait(?Send)]
+pub(crate) trait EventDispatcher {
+    async fn handle(&mut self, shard: ShardInfo, event: String, data: serde_json::Value);
+}
+
+/// Settings shared by the gateway connections of every shard.
+#[derive(Clone)]
+pub(crate) struct GatewayConfig {
+    pub url: String,
+    pub token: String,
+    pub intents: Option<GatewayIntents>,
+    pub compress: bool,
+    pub encoding: Encoding,
+    pub presence: Option<gateway::StatusUpdate>,
+    pub identify_queue: Arc<dyn IdentifyQueue>,
+    pub member_requests: members::MemberRequests,
+    pub waiters: waiters::EventWaiters,
 }
 
 enum DiscordState {
@@ -20,18 +72,31 @@ enum DiscordState {
     Ready,
 }
 
+/// Whatever the gateway has to handle next
+enum Incoming {
+    Payload(gateway::Payload),
+    Command(gateway::Payload),
+    Closed,
+}
+
 use gateway::GatewayOpcode;
 
 pub(crate) struct Gateway<F>
 where
     F: EventHandler,
 {
-    token: String,
+    config: GatewayConfig,
+    shard: ShardInfo,
     client: Client,
-    session_id: String,
+    session_id: Option<String>,
     state: DiscordState,
     event_handler: F,
     socket: Option<Connection>,
+    disconnect: tokio::sync::mpsc::UnboundedSender<bool>,
+    backoff: Backoff,
+    commands: futures_util::stream::Fuse<tokio::sync::mpsc::UnboundedReceiver<gateway::Payload>>,
+    pending_commands: Vec<gateway::Payload>,
+    metrics: Arc<ShardMetrics>,
 
     heartbeat_sender: HeartbeatSender,
     heartbeat_handler: HeartbeatHandler,
@@ -43,12 +108,45 @@ where
 {
     async fn ev_ready(&mut self, payload: &serde_json::Value) -> Result<()> {
         self.state = DiscordState::Ready;
-        self.session_id = payload["session_id"]
-            .as_str()
-            .expect("Ready has no session_id?")
-            .to_owned();
+        self.metrics.set_state(ConnectionState::Connected);
+        self.backoff.reset();
+        self.flush_commands();
+        self.session_id = Some(
+            payload["session_id"]
+                .as_str()
+                .expect("Ready has no session_id?")
+                .to_owned(),
+        );
 
-        log::info!("Discord connection ready!");
+        log::info!(
+            "Discord connection ready! (shard {}/{})",
+            self.shard.id,
+            self.shard.total
+        );
+        Ok(())
+    }
+
+    async fn ev_resumed(&mut self, _payload: &serde_json::Value) -> Result<()> {
+        self.state = DiscordState::Ready;
+        self.metrics.set_state(ConnectionState::Connected);
+        self.metrics.resumes.fetch_add(1, Ordering::Relaxed);
+        self.backoff.reset();
+        self.flush_commands();
+
+        log::info!(
+            "Discord session resumed! (shard {}/{})",
+            self.shard.id,
+            self.shard.total
+        );
+        Ok(())
+    }
+
+    async fn ev_guild_members_chunk(&mut self, payload: &serde_json::Value) -> Result<()> {
+        // A broken chunk shouldn't end the connection, its request just never completes
+        match serde_json::from_value(payload.clone()) {
+            Ok(chunk) => self.config.member_requests.handle_chunk(chunk),
+            Err(e) => log::error!("Cannot parse guild members chunk: {}", e),
+        }
         Ok(())
     }
 
@@ -60,46 +158,60 @@ where
 
         match event.as_str() {
             "READY" => self.ev_ready(&data).await?,
+            "RESUMED" => self.ev_resumed(&data).await?,
+            "GUILD_MEMBERS_CHUNK" => self.ev_guild_members_chunk(&data).await?,
             _ => {}
         }
 
-        self.event_handler.handle(event, data).await
+        self.event_handler.handle(self.shard, event, data).await
     }
 
     async fn op1_heartbeat(&mut self, _payload: gateway::Payload) -> Result<()> {
-        let last_seq = &*self.heartbeat_handler.last_seq.borrow();
-        self.client
-            .send(GatewayOpcode::Heartbeat, serde_json::to_value(last_seq)?)
+        let last_seq = serde_json::to_value(*self.heartbeat_handler.last_seq.borrow())?;
+        self.metrics.heartbeat_sent(std::time::Instant::now());
+        self.send(GatewayOpcode::Heartbeat, last_seq);
+        Ok(())
     }
 
     async fn op7_reconnect(&mut self, _payload: gateway::Payload) -> Result<()> {
-        unimplemented!("Discord Gateway Reconnect")
+        // Closing the socket makes the receive loop in `handle` end, which
+        // will then reconnect and resume the session.
+        log::info!("Discord requested a reconnect");
+        self.close();
+        Ok(())
     }
-    async fn op9_invalid_session(&mut self, _payload: gateway::Payload) -> Result<()> {
-        unimplemented!("Discord Gateway Invalid Session")
+
+    async fn op9_invalid_session(&mut self, payload: gateway::Payload) -> Result<()> {
+        let resumable = payload.d.as_bool().unwrap_or(false);
+        log::warn!("Discord session invalidated (resumable: {})", resumable);
+
+        // Clients should wait a random amount of time between 1 and 5 seconds
+        // before sending a fresh Identify.
+        //  ~ https://discordapp.com/developers/docs/topics/gateway#resuming
+        let wait = rand::thread_rng().gen_range(1000, 5000);
+        tokio::time::delay_for(std::time::Duration::from_millis(wait)).await;
+
+        // Identifying again goes through the identify queue, like on a new
+        // connection.
+        let last_seq = *self.heartbeat_handler.last_seq.borrow();
+        if !reconnect::resumes_invalid_session(resumable, self.session_id.as_deref(), last_seq) {
+            self.invalidate_session()?;
+        }
+        self.send_identify_or_resume().await
     }
 
     async fn op10_hello(&mut self, payload: gateway::Payload) -> Result<()> {
         if let Some(heartbeat_interval) = payload.d["heartbeat_interval"].as_u64() {
             let sender = self.client.send.clone();
             let handler = self.heartbeat_handler.clone();
+            let metrics = self.metrics.clone();
 
             log::trace!("heartbeat interval: {} ms", heartbeat_interval);
             tokio::spawn(async move {
-                heartbeat(sender, heartbeat_interval, handler).await;
+                heartbeat(sender, heartbeat_interval, handler, metrics).await;
             });
 
-            self.client.send(
-                GatewayOpcode::Identify,
-                json!({
-                    "token": self.token,
-                    "properties": {
-                        "$os": std::env::consts::OS,
-                        "$browser": crate::LIBRARY_IDENTITY,
-                        "$device": crate::LIBRARY_IDENTITY
-                    }
-                }),
-            )
+            self.send_identify_or_resume().await
         } else {
             Err(GatewayError::InvalidResponseError {
                 what: "Hello does not have heartbeat_interval".to_owned(),
@@ -113,6 +225,7 @@ where
         // sending heartbeats, it should immediately terminate the connection
         // with a non-1000 close code, reconnect, and attempt to resume.
         //  ~ https://discordapp.com/developers/docs/topics/gateway#connecting-to-the-gateway
+        self.metrics.heartbeat_acked(std::time::Instant::now());
         self.heartbeat_sender
             .last_ack
             .broadcast(std::time::Instant::now())
@@ -120,9 +233,114 @@ where
         Ok(())
     }
 
-    pub async fn new(gateway: &str, token: &str, event_handler: F) -> Result<Self> {
-        let mut builder = ClientBuilder::new(&format!("{}?v=6&encoding=json", gateway))
-            .map_err(GatewayError::from)?;
+    /// Forgets the current session, so that the next connection
+    /// identifies as a new one instead of resuming.
+    fn invalidate_session(&mut self) -> Result<()> {
+        self.state = DiscordState::Initial;
+        self.session_id = None;
+        self.heartbeat_sender.last_seq.broadcast(None)?;
+        Ok(())
+    }
+
+    /// Resumes the previous session if there is one, or identifies
+    /// as a new session otherwise.
+    ///
+    /// Identifying waits for the identify queue first. That only happens
+    /// once the connection is open, so that a connection that fails to
+    /// open doesn't use up an identify.
+    async fn send_identify_or_resume(&mut self) -> Result<()> {
+        let last_seq = *self.heartbeat_handler.last_seq.borrow();
+
+        match reconnect::handshake(self.session_id.as_deref(), last_seq) {
+            Handshake::Resume { session_id, seq } => {
+                log::info!("Resuming session {} at sequence {}", session_id, seq);
+                let resume = json!({
+                    "token": self.config.token,
+                    "session_id": session_id,
+                    "seq": seq,
+                });
+                self.metrics.set_state(ConnectionState::Resuming);
+                self.send(GatewayOpcode::Resume, resume);
+            }
+            Handshake::Identify => {
+                let mut identify = json!({
+                    "token": self.config.token,
+                    "shard": [self.shard.id, self.shard.total],
+                    "properties": {
+                        "$os": std::env::consts::OS,
+                        "$browser": crate::LIBRARY_IDENTITY,
+                        "$device": crate::LIBRARY_IDENTITY
+                    }
+                });
+                if let Some(intents) = self.config.intents {
+                    identify["intents"] = json!(intents);
+                }
+                if let Some(presence) = &self.config.presence {
+                    identify["presence"] = serde_json::to_value(presence)?;
+                }
+
+                self.metrics.set_state(ConnectionState::Identifying);
+                self.config.identify_queue.acquire(self.shard).await?;
+                self.send(GatewayOpcode::Identify, identify);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a payload on the current connection.
+    ///
+    /// Discord often closes the connection right after a payload, like a
+    /// Reconnect, so the socket task can be gone before the payload is
+    /// handled. The receive loop then sees the connection closed and
+    /// reconnects, so the payload is dropped instead of failing.
+    fn send(&mut self, op: GatewayOpcode, value: serde_json::Value) {
+        if self.client.send(op, value).is_err() {
+            log::debug!("Not sending {:?}, the gateway connection is closed", op);
+        }
+    }
+
+    /// Closes the current connection, so that the receive loop reconnects.
+    fn close(&self) {
+        if self.disconnect.send(true).is_err() {
+            log::debug!("The gateway connection is already closed");
+        }
+    }
+
+    /// Sends a command from the `Discord` client, or holds it back
+    /// until the session is ready.
+    fn send_command(&mut self, command: gateway::Payload) {
+        if let DiscordState::Initial = self.state {
+            self.pending_commands.push(command);
+            return;
+        }
+
+        // If the connection is already gone, send it after reconnecting
+        if let Err(tokio::sync::mpsc::error::SendError(command)) = self.client.send.send(command) {
+            self.pending_commands.push(command);
+        }
+    }
+
+    /// Sends the commands that were held back while the session wasn't ready.
+    fn flush_commands(&mut self) {
+        for command in std::mem::take(&mut self.pending_commands) {
+            self.send_command(command);
+        }
+    }
+
+    /// Opens a new web socket connection to the gateway.
+    async fn open(
+        config: &GatewayConfig,
+        metrics: &Arc<ShardMetrics>,
+    ) -> Result<(Connection, Client, tokio::sync::mpsc::UnboundedSender<bool>)> {
+        let mut url = format!(
+            "{}?v=6&encoding={}",
+            config.url,
+            config.encoding.query_name()
+        );
+        if config.compress {
+            url.push_str("&compress=zlib-stream");
+        }
+        let mut builder = ClientBuilder::new(&url).map_err(GatewayError::from)?;
 
         builder.add_header(
             "User-Agent".to_owned(),
@@ -131,35 +349,190 @@ where
 
         if let Ok(client) = builder.async_connect().await {
             let (disconnect_tx, disconnect_rx) = tokio::sync::mpsc::unbounded_channel::<bool>();
-            let (connection, client) = Connection::new(client, disconnect_rx);
-            let (handler, sender) = HeartbeatHandler::new(disconnect_tx);
-
-            Ok(Gateway {
-                token: token.to_owned(),
+            let (connection, client) = Connection::new(
                 client,
-                socket: Some(connection),
-                event_handler,
-
-                session_id: "".to_owned(),
-                state: DiscordState::Initial,
-
-                heartbeat_sender: sender,
-                heartbeat_handler: handler,
-            })
+                disconnect_rx,
+                config.compress,
+                config.encoding,
+                metrics.clone(),
+            );
+            Ok((connection, client, disconnect_tx))
         } else {
             Err(GatewayError::ConnectError.into())
         }
     }
 
+    /// Keeps trying to open a connection, waiting longer after every
+    /// failed attempt.
+    async fn open_with_backoff(
+        config: &GatewayConfig,
+        metrics: &Arc<ShardMetrics>,
+        backoff: &mut Backoff,
+    ) -> (Connection, Client, tokio::sync::mpsc::UnboundedSender<bool>) {
+        loop {
+            match Self::open(config, metrics).await {
+                Ok(opened) => return opened,
+                Err(e) => log::error!("Cannot connect to the gateway: {}", e),
+            }
+
+            metrics.set_state(ConnectionState::Reconnecting);
+            let delay = backoff.next_delay();
+            log::info!("Connecting to the gateway again in {:?}", delay);
+            tokio::time::delay_for(delay).await;
+            metrics.set_state(ConnectionState::Connecting);
+        }
+    }
+
+    /// Connects to the gateway, retrying until the connection opens.
+    pub async fn new(
+        config: GatewayConfig,
+        shard: ShardInfo,
+        commands: tokio::sync::mpsc::UnboundedReceiver<gateway::Payload>,
+        metrics: Arc<ShardMetrics>,
+        event_handler: F,
+    ) -> Self {
+        let mut backoff = Backoff::default();
+        let (connection, client, disconnect) =
+            Self::open_with_backoff(&config, &metrics, &mut backoff).await;
+        let (handler, sender) = HeartbeatHandler::new(disconnect.clone(), None);
+
+        Gateway {
+            config,
+            shard,
+            client,
+            socket: Some(connection),
+            disconnect,
+            backoff,
+            commands: commands.fuse(),
+            pending_commands: Vec::new(),
+            metrics,
+            event_handler,
+
+            session_id: None,
+            state: DiscordState::Initial,
+
+            heartbeat_sender: sender,
+            heartbeat_handler: handler,
+        }
+    }
+
+    /// Replaces the current connection with a new one, keeping the
+    /// session id and the last sequence number so that the session
+    /// can be resumed.
+    async fn reconnect(&mut self) {
+        self.metrics.set_state(ConnectionState::Connecting);
+        let last_seq = *self.heartbeat_handler.last_seq.borrow();
+        let (connection, client, disconnect) =
+            Self::open_with_backoff(&self.config, &self.metrics, &mut self.backoff).await;
+        let (handler, sender) = HeartbeatHandler::new(disconnect.clone(), last_seq);
+
+        self.metrics.reconnects.fetch_add(1, Ordering::Relaxed);
+        self.state = DiscordState::Initial;
+        self.client = client;
+        self.socket = Some(connection);
+        self.disconnect = disconnect;
+        self.heartbeat_sender = sender;
+        self.heartbeat_handler = handler;
+    }
+
+    /// Runs the gateway connection, reconnecting whenever it drops.
+    ///
+    /// This only returns if the connection was closed in a way that
+    /// reconnecting can not fix.
     pub(crate) async fn handle(&mut self) -> Result<()> {
-        let socket = self.socket.take().expect("Handle can only be run once");
-        socket.run();
-        while let Some(payload) = self.client.receive().await {
-            self.handle_payload(payload).await?;
+        let result = self.run().await;
+        self.metrics.set_state(ConnectionState::Disconnected);
+        result
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        loop {
+            let socket = self.socket.take().expect("Socket is already running");
+            let connection = socket.run();
+            loop {
+                match self.next_incoming().await {
+                    Incoming::Payload(payload) => {
+                        // The payloads after this one might depend on it, so
+                        // it's safer to start over with a new connection.
+                        if let Err(e) = self.handle_payload(payload).await {
+                            log::error!("Cannot handle a gateway payload, reconnecting: {}", e);
+                            self.close();
+                        }
+                    }
+                    Incoming::Command(command) => self.send_command(command),
+                    Incoming::Closed => break,
+                }
+            }
+
+            match connection.await {
+                Ok(Ok(Some(frame))) => self.handle_close(frame)?,
+                Ok(Ok(None)) => log::warn!("Gateway connection closed, reconnecting"),
+                // An Identify or Resume that is too large fails the same way again
+                Ok(Err(e @ DiscordError::GatewayError(GatewayError::PayloadTooLarge { .. }))) => {
+                    return Err(e)
+                }
+                Ok(Err(e)) => log::error!("Gateway connection failed, reconnecting: {}", e),
+                Err(e) => log::error!("Gateway socket task failed, reconnecting: {}", e),
+            }
+
+            self.reconnect_with_backoff().await;
+        }
+    }
+
+    /// Waits for the next payload from Discord, or the next command
+    /// from the client.
+    async fn next_incoming(&mut self) -> Incoming {
+        let receive = self.client.receive().fuse();
+        futures_util::pin_mut!(receive);
+
+        futures_util::select! {
+            payload = receive => payload.map_or(Incoming::Closed, Incoming::Payload),
+            command = self.commands.select_next_some() => Incoming::Command(command),
+        }
+    }
+
+    /// Decides what to do with the session after Discord closed the connection.
+    fn handle_close(&mut self, frame: CloseFrame) -> Result<()> {
+        let description = reconnect::describe(frame.code);
+        if reconnect::is_fatal(frame.code) {
+            log::error!(
+                "Gateway closed with fatal code {} ({}): {}",
+                frame.code,
+                description,
+                frame.reason
+            );
+            let reason = if frame.reason.is_empty() {
+                description.to_owned()
+            } else {
+                frame.reason
+            };
+            return Err(GatewayError::FatalClose {
+                code: frame.code,
+                reason,
+            }
+            .into());
+        }
+
+        log::warn!(
+            "Gateway closed with code {} ({}), reconnecting",
+            frame.code,
+            description
+        );
+        if reconnect::invalidates_session(frame.code) {
+            self.invalidate_session()?;
         }
         Ok(())
     }
 
+    /// Waits before reconnecting, longer after every dropped connection.
+    async fn reconnect_with_backoff(&mut self) {
+        self.metrics.set_state(ConnectionState::Reconnecting);
+        let delay = self.backoff.next_delay();
+        log::info!("Reconnecting to the gateway in {:?}", delay);
+        tokio::time::delay_for(delay).await;
+        self.reconnect().await;
+    }
+
     async fn handle_payload(&mut self, payload: gateway::Payload) -> Result<()> {
         use std::convert::TryFrom;
 
@@ -0,0 +1,65 @@
+use crate::api::gateway::Payload;
+use crate::errors::{GatewayError, Result};
+use websocket_lite::Message;
+
+/// Discord closes the connection when a payload is larger than this.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#sending-payloads
+pub(crate) const MAX_PAYLOAD_SIZE: usize = 4096;
+
+/// The format gateway payloads are sent in.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#encoding-and-compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    /// Erlang's External Term Format, which is smaller and faster to
+    /// decode than JSON. Needs the `etf` feature.
+    #[cfg(feature = "etf")]
+    Etf,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Json
+    }
+}
+
+impl Encoding {
+    /// The value of the `encoding` query parameter for this encoding
+    pub(crate) fn query_name(self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            #[cfg(feature = "etf")]
+            Encoding::Etf => "etf",
+        }
+    }
+
+    /// Decodes a complete (and decompressed) message into a payload
+    pub(crate) fn decode(self, data: &[u8]) -> Result<Payload> {
+        match self {
+            Encoding::Json => Ok(serde_json::from_slice(data)?),
+            #[cfg(feature = "etf")]
+            Encoding::Etf => super::etf::decode_payload(data),
+        }
+    }
+
+    /// Encodes a payload into the message that is sent to Discord
+    pub(crate) fn encode(self, payload: &Payload) -> Result<Message> {
+        match self {
+            Encoding::Json => Ok(Message::text(serde_json::to_string(payload)?)),
+            #[cfg(feature = "etf")]
+            Encoding::Etf => Ok(Message::binary(super::etf::encode_payload(payload)?)),
+        }
+    }
+
+    /// Encodes a payload like `encode`, but fails if it's too large to send
+    pub(crate) fn encode_checked(self, payload: &Payload) -> Result<Message> {
+        let message = self.encode(payload)?;
+        let size = message.data().len();
+        if size > MAX_PAYLOAD_SIZE {
+            return Err(GatewayError::PayloadTooLarge { size }.into());
+        }
+        Ok(message)
+    }
+}
@@ -0,0 +1,334 @@
+//! A minimal Erlang External Term Format codec, converting between
+//! the terms Discord sends and `serde_json::Value`s.
+//!
+//! Payloads are decoded straight into a `Payload`, whose data is the
+//! decoded `Value`. That is the same tree the JSON encoding builds, so the
+//! events are parsed from it the same way, without another copy of it.
+//!
+//! http://erlang.org/doc/apps/erts/erl_ext_dist.html
+//! https://discordapp.com/developers/docs/topics/gateway#etf-erlang-term-format
+
+use crate::api::gateway::Payload;
+use crate::errors::{GatewayError, Result};
+use serde_json::{Map, Number, Value};
+
+const FORMAT_VERSION: u8 = 131;
+
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+fn invalid(what: &str) -> crate::errors::DiscordError {
+    GatewayError::EtfError {
+        what: what.to_owned(),
+    }
+    .into()
+}
+
+/// Decodes a whole ETF message into a JSON value.
+pub(crate) fn decode(data: &[u8]) -> Result<Value> {
+    let mut decoder = Decoder { data, position: 0 };
+    if decoder.read_u8()? != FORMAT_VERSION {
+        return Err(invalid("unknown format version"));
+    }
+    decoder.read_term()
+}
+
+/// Decodes a whole ETF message into a gateway payload.
+pub(crate) fn decode_payload(data: &[u8]) -> Result<Payload> {
+    let mut payload = match decode(data)? {
+        Value::Object(payload) => payload,
+        _ => return Err(invalid("payload is not a map")),
+    };
+
+    let op = payload
+        .get("op")
+        .and_then(Value::as_u64)
+        .filter(|op| *op <= u64::from(u8::MAX))
+        .ok_or_else(|| invalid("payload has no opcode"))?;
+    let t = match payload.remove("t") {
+        Some(Value::String(t)) => Some(t),
+        _ => None,
+    };
+    Ok(Payload {
+        op: op as u8,
+        d: payload.remove("d").unwrap_or(Value::Null),
+        s: payload.get("s").and_then(Value::as_u64),
+        t,
+    })
+}
+
+/// Encodes a gateway payload as an ETF message.
+pub(crate) fn encode_payload(payload: &Payload) -> Result<Vec<u8>> {
+    let mut output = vec![FORMAT_VERSION, MAP_EXT];
+    output.extend_from_slice(&4u32.to_be_bytes());
+    write_binary(&mut output, b"op");
+    write_term(&mut output, &Value::from(payload.op))?;
+    write_binary(&mut output, b"d");
+    write_term(&mut output, &payload.d)?;
+    write_binary(&mut output, b"s");
+    match payload.s {
+        Some(s) => write_term(&mut output, &Value::from(s))?,
+        None => write_atom(&mut output, "nil"),
+    }
+    write_binary(&mut output, b"t");
+    match &payload.t {
+        Some(t) => write_binary(&mut output, t.as_bytes()),
+        None => write_atom(&mut output, "nil"),
+    }
+    Ok(output)
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_string(&mut self, length: usize) -> Result<String> {
+        let bytes = self.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+    }
+
+    fn read_term(&mut self) -> Result<Value> {
+        match self.read_u8()? {
+            SMALL_INTEGER_EXT => Ok(Value::from(self.read_u8()?)),
+            INTEGER_EXT => Ok(Value::from(self.read_u32()? as i32)),
+            NEW_FLOAT_EXT => {
+                let bytes = self.read_bytes(8)?;
+                let mut float = [0; 8];
+                float.copy_from_slice(bytes);
+                Ok(Number::from_f64(f64::from_be_bytes(float))
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null))
+            }
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                let length = self.read_u16()? as usize;
+                self.read_atom(length)
+            }
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let length = self.read_u8()? as usize;
+                self.read_atom(length)
+            }
+            SMALL_TUPLE_EXT => {
+                let arity = self.read_u8()? as usize;
+                self.read_list(arity)
+            }
+            LARGE_TUPLE_EXT => {
+                let arity = self.read_u32()? as usize;
+                self.read_list(arity)
+            }
+            NIL_EXT => Ok(Value::Array(Vec::new())),
+            STRING_EXT => {
+                // A list of bytes, which is how Erlang represents short strings
+                let length = self.read_u16()? as usize;
+                let bytes = self.read_bytes(length)?;
+                Ok(Value::Array(
+                    bytes.iter().map(|b| Value::from(*b)).collect(),
+                ))
+            }
+            LIST_EXT => {
+                let length = self.read_u32()? as usize;
+                let list = self.read_list(length)?;
+                // Proper lists end with an empty list as the tail
+                if self.read_term()? != Value::Array(Vec::new()) {
+                    return Err(invalid("improper list"));
+                }
+                Ok(list)
+            }
+            BINARY_EXT => {
+                let length = self.read_u32()? as usize;
+                Ok(Value::String(self.read_string(length)?))
+            }
+            SMALL_BIG_EXT => {
+                let length = self.read_u8()? as usize;
+                self.read_big(length)
+            }
+            LARGE_BIG_EXT => {
+                let length = self.read_u32()? as usize;
+                self.read_big(length)
+            }
+            MAP_EXT => {
+                let arity = self.read_u32()? as usize;
+                let mut map = Map::new();
+                for _ in 0..arity {
+                    let key = self.read_key()?;
+                    map.insert(key, self.read_term()?);
+                }
+                Ok(Value::Object(map))
+            }
+            _ => Err(invalid("unsupported term")),
+        }
+    }
+
+    /// Reads a map key. Atoms are kept as they are, so keys like `nil`
+    /// don't turn into non-string values.
+    fn read_key(&mut self) -> Result<String> {
+        let length = match self.data.get(self.position) {
+            Some(&ATOM_EXT) | Some(&ATOM_UTF8_EXT) => {
+                self.position += 1;
+                self.read_u16()? as usize
+            }
+            Some(&SMALL_ATOM_EXT) | Some(&SMALL_ATOM_UTF8_EXT) => {
+                self.position += 1;
+                self.read_u8()? as usize
+            }
+            _ => {
+                return match self.read_term()? {
+                    Value::String(key) => Ok(key),
+                    Value::Number(key) => Ok(key.to_string()),
+                    _ => Err(invalid("map key is not a string")),
+                }
+            }
+        };
+        self.read_string(length)
+    }
+
+    fn read_atom(&mut self, length: usize) -> Result<Value> {
+        Ok(match self.read_string(length)?.as_str() {
+            "nil" | "null" => Value::Null,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            atom => Value::String(atom.to_owned()),
+        })
+    }
+
+    fn read_list(&mut self, length: usize) -> Result<Value> {
+        // Don't trust the length for the allocation, it can be anything
+        let mut list = Vec::with_capacity(length.min(self.data.len()));
+        for _ in 0..length {
+            list.push(self.read_term()?);
+        }
+        Ok(Value::Array(list))
+    }
+
+    /// Reads a big integer, which is how Discord sends snowflakes
+    fn read_big(&mut self, length: usize) -> Result<Value> {
+        let negative = self.read_u8()? != 0;
+        let digits = self.read_bytes(length)?;
+        if length > 8 {
+            return Err(invalid("integer does not fit in 64 bits"));
+        }
+
+        let value = digits
+            .iter()
+            .rev()
+            .fold(0u64, |value, digit| (value << 8) | u64::from(*digit));
+        if !negative {
+            Ok(Value::from(value))
+        } else if value <= i64::MAX as u64 + 1 {
+            Ok(Value::from((value as i64).wrapping_neg()))
+        } else {
+            Err(invalid("integer does not fit in 64 bits"))
+        }
+    }
+}
+
+fn write_atom(output: &mut Vec<u8>, atom: &str) {
+    output.push(SMALL_ATOM_UTF8_EXT);
+    output.push(atom.len() as u8);
+    output.extend_from_slice(atom.as_bytes());
+}
+
+fn write_binary(output: &mut Vec<u8>, data: &[u8]) {
+    output.push(BINARY_EXT);
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    output.extend_from_slice(data);
+}
+
+fn write_term(output: &mut Vec<u8>, value: &Value) -> Result<()> {
+    match value {
+        Value::Null => write_atom(output, "nil"),
+        Value::Bool(true) => write_atom(output, "true"),
+        Value::Bool(false) => write_atom(output, "false"),
+        Value::Number(number) => {
+            if let Some(small) = number.as_u64().filter(|n| *n <= u64::from(u8::MAX)) {
+                output.push(SMALL_INTEGER_EXT);
+                output.push(small as u8);
+            } else if let Some(int) = number
+                .as_i64()
+                .filter(|n| *n >= i64::from(i32::MIN) && *n <= i64::from(i32::MAX))
+            {
+                output.push(INTEGER_EXT);
+                output.extend_from_slice(&(int as i32).to_be_bytes());
+            } else if let Some(big) = number.as_u64() {
+                write_big(output, false, big);
+            } else if let Some(big) = number.as_i64() {
+                // Only negative numbers are left, as_u64 takes the others
+                write_big(output, true, (big as u64).wrapping_neg());
+            } else if let Some(float) = number.as_f64() {
+                output.push(NEW_FLOAT_EXT);
+                output.extend_from_slice(&float.to_be_bytes());
+            } else {
+                return Err(invalid("unsupported number"));
+            }
+        }
+        Value::String(string) => write_binary(output, string.as_bytes()),
+        Value::Array(list) => {
+            if !list.is_empty() {
+                output.push(LIST_EXT);
+                output.extend_from_slice(&(list.len() as u32).to_be_bytes());
+                for item in list {
+                    write_term(output, item)?;
+                }
+            }
+            output.push(NIL_EXT);
+        }
+        Value::Object(map) => {
+            output.push(MAP_EXT);
+            output.extend_from_slice(&(map.len() as u32).to_be_bytes());
+            for (key, item) in map {
+                write_binary(output, key.as_bytes());
+                write_term(output, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_big(output: &mut Vec<u8>, negative: bool, value: u64) {
+    let digits = value.to_le_bytes();
+    let length = digits.iter().rposition(|d| *d != 0).map_or(0, |i| i + 1);
+    output.push(SMALL_BIG_EXT);
+    output.push(length as u8);
+    output.push(negative as u8);
+    output.extend_from_slice(&digits[..length]);
+}
@@ -1,4 +1,7 @@
+use super::status::ShardMetrics;
 use crate::api::gateway;
+use rand::Rng;
+use std::sync::Arc;
 use tokio::sync::watch;
 
 pub struct HeartbeatSender {
@@ -17,10 +20,11 @@ pub struct HeartbeatHandler {
 impl HeartbeatHandler {
     pub fn new(
         disconnect_tx: tokio::sync::mpsc::UnboundedSender<bool>,
+        last_seq: Option<u64>,
     ) -> (HeartbeatHandler, HeartbeatSender) {
         let start = std::time::Instant::now();
         let (last_ack_tx, last_ack_rx) = watch::channel::<std::time::Instant>(start);
-        let (last_seq_tx, last_seq_rx) = watch::channel::<Option<u64>>(None);
+        let (last_seq_tx, last_seq_rx) = watch::channel::<Option<u64>>(last_seq);
         let handler = HeartbeatHandler {
             last_ack: last_ack_rx,
             last_seq: last_seq_rx,
@@ -39,9 +43,18 @@ pub async fn heartbeat(
     client: tokio::sync::mpsc::UnboundedSender<gateway::Payload>,
     heartbeat_interval: u64,
     mut handler: HeartbeatHandler,
+    metrics: Arc<ShardMetrics>,
 ) {
-    let mut interval =
-        tokio::time::interval(tokio::time::Duration::from_millis(heartbeat_interval));
+    let heartbeat_interval = tokio::time::Duration::from_millis(heartbeat_interval);
+
+    // The first heartbeat should be sent after heartbeat_interval * jitter,
+    // where jitter is a random value between 0 and 1, so that clients
+    // reconnecting at the same time don't all heartbeat at the same time.
+    //  ~ https://discordapp.com/developers/docs/topics/gateway#heartbeating
+    let jitter = rand::thread_rng().gen_range(0.0, 1.0);
+    tokio::time::delay_for(heartbeat_interval.mul_f64(jitter)).await;
+
+    let mut interval = tokio::time::interval(heartbeat_interval);
 
     loop {
         interval.tick().await;
@@ -49,11 +62,12 @@ pub async fn heartbeat(
         if *handler.last_ack.borrow() < handler.last_send {
             // Break connection
             log::error!("No response for heartbeat, reconnecting");
-            handler.disconnect.send(true).unwrap();
+            let _ = handler.disconnect.send(true);
             break;
         }
 
         handler.last_send = std::time::Instant::now();
+        metrics.heartbeat_sent(handler.last_send);
 
         log::trace!("Sending heartbeat");
         let value: serde_json::Value = serde_json::to_value(*handler.last_seq.borrow())
@@ -64,7 +78,9 @@ pub async fn heartbeat(
             s: None,
             t: None,
         }) {
-            log::error!("Cannot send heartbeat to Discord: {}", e);
+            // The connection this heartbeat belonged to is gone
+            log::debug!("Stopping heartbeat: {}", e);
+            break;
         }
     }
 }
@@ -0,0 +1,119 @@
+use super::ShardInfo;
+use crate::api::gateway::SessionStartLimit;
+use crate::errors::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// How long a shard has to wait after another shard of the same
+/// bucket identified.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#identifying
+const IDENTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Decides when shards are allowed to identify.
+///
+/// Implement this to share the identify limits between multiple processes
+/// running shards of the same bot, for example by asking a coordinator
+/// running on the same host.
+#[async_trait]
+pub trait IdentifyQueue: Send + Sync {
+    /// Updates the limits with what Discord reported from `/gateway/bot`.
+    /// This is called every time `Discord::connect` runs.
+    async fn set_limit(&self, limit: SessionStartLimit);
+
+    /// Waits until the given shard is allowed to identify, and counts
+    /// the identify against the limits. This is called once the connection
+    /// of the shard is open, and an error makes the shard reconnect.
+    async fn acquire(&self, shard: ShardInfo) -> Result<()>;
+}
+
+struct Budget {
+    total: u32,
+    remaining: u32,
+    reset_at: Instant,
+}
+
+/// The default `IdentifyQueue`, which shares the limits between
+/// all of the shards in a single process.
+pub struct LocalIdentifyQueue {
+    budget: Mutex<Budget>,
+    buckets: Mutex<Vec<Arc<Mutex<Option<Instant>>>>>,
+}
+
+impl LocalIdentifyQueue {
+    pub fn new() -> Self {
+        LocalIdentifyQueue {
+            budget: Mutex::new(Budget {
+                total: 1000,
+                remaining: 1000,
+                reset_at: Instant::now(),
+            }),
+            buckets: Mutex::new(vec![Default::default()]),
+        }
+    }
+
+    /// Waits until the daily identify budget has room for one more
+    /// identify, and takes it.
+    async fn take_budget(&self) {
+        let mut budget = self.budget.lock().await;
+        if budget.remaining == 0 {
+            let now = Instant::now();
+            if budget.reset_at > now {
+                log::warn!(
+                    "Identify limit reached, waiting {:?} before identifying",
+                    budget.reset_at - now
+                );
+                tokio::time::delay_until(budget.reset_at).await;
+            }
+            budget.remaining = budget.total;
+            budget.reset_at = Instant::now() + Duration::from_secs(24 * 60 * 60);
+        }
+        budget.remaining -= 1;
+    }
+}
+
+impl Default for LocalIdentifyQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl IdentifyQueue for LocalIdentifyQueue {
+    async fn set_limit(&self, limit: SessionStartLimit) {
+        log::debug!("Session start limit: {:?}", limit);
+        {
+            let mut budget = self.budget.lock().await;
+            budget.total = limit.total;
+            budget.remaining = limit.remaining;
+            budget.reset_at = Instant::now() + Duration::from_millis(limit.reset_after.into());
+        }
+
+        let mut buckets = self.buckets.lock().await;
+        let max_concurrency = limit.max_concurrency.max(1) as usize;
+        buckets.resize_with(max_concurrency, Default::default);
+    }
+
+    async fn acquire(&self, shard: ShardInfo) -> Result<()> {
+        let bucket = {
+            let buckets = self.buckets.lock().await;
+            buckets[shard.id as usize % buckets.len()].clone()
+        };
+
+        // Holding the bucket lock makes the other shards of the bucket wait
+        // until this one is done.
+        let mut last_identify = bucket.lock().await;
+        if let Some(last) = *last_identify {
+            tokio::time::delay_until(last + IDENTIFY_INTERVAL).await;
+        }
+
+        self.take_budget().await;
+        *last_identify = Some(Instant::now());
+
+        log::debug!("Shard {}/{} may identify", shard.id, shard.total);
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use crate::errors::Result;
+use flate2::{Decompress, FlushDecompress};
+
+/// Every complete zlib-stream message ends with this suffix
+///
+/// https://discordapp.com/developers/docs/topics/gateway#transport-compression
+const ZLIB_SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Decompresses the messages of a `compress=zlib-stream` connection.
+///
+/// The whole connection shares a single zlib context, so one of these
+/// has to live as long as the connection does.
+pub(crate) struct Inflater {
+    decompress: Decompress,
+    buffer: Vec<u8>,
+}
+
+impl Inflater {
+    pub(crate) fn new() -> Self {
+        Inflater {
+            decompress: Decompress::new(true),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Adds a binary frame to the stream.
+    ///
+    /// Messages may be split over multiple frames, so this only returns
+    /// the decompressed message once the last frame of it arrives.
+    pub(crate) fn push(&mut self, frame: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.buffer.extend_from_slice(frame);
+        if !self.buffer.ends_with(&ZLIB_SUFFIX) {
+            return Ok(None);
+        }
+
+        let mut output = Vec::with_capacity(self.buffer.len() * 4);
+        let mut offset = 0;
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(1024));
+            }
+
+            let before_in = self.decompress.total_in();
+            let before_out = self.decompress.total_out();
+            self.decompress.decompress_vec(
+                &self.buffer[offset..],
+                &mut output,
+                FlushDecompress::Sync,
+            )?;
+            let consumed = (self.decompress.total_in() - before_in) as usize;
+            let produced = self.decompress.total_out() - before_out;
+            offset += consumed;
+
+            // Done once all the input is used, and the output didn't fill up
+            // (which would mean there is more output waiting).
+            let finished = offset == self.buffer.len() && output.len() < output.capacity();
+            if finished || (consumed == 0 && produced == 0) {
+                break;
+            }
+        }
+
+        self.buffer.clear();
+        Ok(Some(output))
+    }
+}
@@ -0,0 +1,81 @@
+use crate::api::guild::{MemberChunk, RequestedMembers};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::oneshot;
+
+struct PendingRequest {
+    members: RequestedMembers,
+    received: Vec<bool>,
+    sender: oneshot::Sender<RequestedMembers>,
+}
+
+/// Collects the `GUILD_MEMBERS_CHUNK` events sent for member requests,
+/// until every chunk of a request has arrived.
+#[derive(Clone, Default)]
+pub(crate) struct MemberRequests {
+    next_nonce: Arc<AtomicU64>,
+    pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
+}
+
+impl MemberRequests {
+    /// Starts collecting a new request, returning the nonce to send it
+    /// with, and the receiver that gets every member once they arrive.
+    pub(crate) fn register(&self) -> (String, oneshot::Receiver<RequestedMembers>) {
+        let nonce = self.next_nonce.fetch_add(1, Ordering::Relaxed).to_string();
+        let (sender, receiver) = oneshot::channel();
+
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        // Forget the requests nobody is waiting for anymore
+        pending.retain(|_, request| !request.sender.is_closed());
+        pending.insert(
+            nonce.clone(),
+            PendingRequest {
+                members: RequestedMembers::default(),
+                received: Vec::new(),
+                sender,
+            },
+        );
+        (nonce, receiver)
+    }
+
+    /// Adds a chunk to its request, completing the request if it was the last one.
+    pub(crate) fn handle_chunk(&self, chunk: MemberChunk) {
+        let nonce = match chunk.nonce {
+            Some(nonce) => nonce,
+            None => return,
+        };
+
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let request = match pending.get_mut(&nonce) {
+            Some(request) => request,
+            None => return,
+        };
+
+        let count = chunk.chunk_count.max(1) as usize;
+        request.received.resize(count, false);
+        if let Some(received) = request.received.get_mut(chunk.chunk_index as usize) {
+            *received = true;
+        }
+
+        let members = &mut request.members;
+        members.members.extend(chunk.members);
+        members
+            .presences
+            .extend(chunk.presences.unwrap_or_default());
+        members
+            .not_found
+            .extend(chunk.not_found.unwrap_or_default());
+
+        if request.received.iter().all(|r| *r) {
+            if let Some(request) = pending.remove(&nonce) {
+                log::debug!(
+                    "Received all {} members of request {}",
+                    request.members.members.len(),
+                    nonce
+                );
+                let _ = request.sender.send(request.members);
+            }
+        }
+    }
+}
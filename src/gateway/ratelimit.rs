@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+/// Discord closes connections that send more than 120 payloads in 60 seconds.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#rate-limiting
+const GATEWAY_LIMIT: u32 = 120;
+const GATEWAY_PERIOD: Duration = Duration::from_secs(60);
+
+/// How many tokens only heartbeats, identifies and resumes can use, so
+/// that a burst of commands can't delay them.
+pub(crate) const RESERVED: u32 = 5;
+
+/// A token bucket, where every sent payload takes a token.
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket, which refills `capacity` tokens every `period`.
+    pub(crate) fn new(capacity: u32, period: Duration, now: Instant) -> Self {
+        TokenBucket {
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            per_second: f64::from(capacity) / period.as_secs_f64(),
+            last_refill: now,
+        }
+    }
+
+    /// A bucket for a gateway connection.
+    ///
+    /// Emptying a full bucket and then using everything that refills during
+    /// the period sends twice the capacity, so half of the limit is used.
+    pub(crate) fn for_gateway(now: Instant) -> Self {
+        Self::new(GATEWAY_LIMIT / 2, GATEWAY_PERIOD, now)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes a token if one is available without going into the `reserved` ones.
+    pub(crate) fn try_take(&mut self, reserved: u32, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= f64::from(reserved) + 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets how long it takes until `try_take` with `reserved` succeeds.
+    pub(crate) fn wait_time(&mut self, reserved: u32, now: Instant) -> Duration {
+        self.refill(now);
+        let missing = f64::from(reserved) + 1.0 - self.tokens;
+        if missing <= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(missing / self.per_second)
+        }
+    }
+}
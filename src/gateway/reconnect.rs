@@ -0,0 +1,123 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Returns whether reconnecting after the given close code is pointless,
+/// because the next connection would be closed the same way.
+///
+/// https://discordapp.com/developers/docs/topics/opcodes-and-status-codes#gateway-gateway-close-event-codes
+pub(crate) fn is_fatal(code: u16) -> bool {
+    matches!(
+        code,
+        4004 // Authentication failed
+        | 4010 // Invalid shard
+        | 4011 // Sharding required
+        | 4012 // Invalid API version
+        | 4013 // Invalid intents
+        | 4014 // Disallowed intents
+    )
+}
+
+/// Returns whether the session can not be resumed after the given close code,
+/// and a new one has to be identified instead.
+pub(crate) fn invalidates_session(code: u16) -> bool {
+    matches!(
+        code,
+        4007 // Invalid seq
+        | 4009 // Session timed out
+    )
+}
+
+/// How a new connection starts its session.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Handshake<'a> {
+    /// Resumes the previous session, replaying the events after `seq`
+    Resume { session_id: &'a str, seq: u64 },
+    /// Identifies as a new session
+    Identify,
+}
+
+/// Decides how to start the session on a new connection. A session can
+/// only be resumed if it has an id, and received at least one event.
+pub(crate) fn handshake(session_id: Option<&str>, last_seq: Option<u64>) -> Handshake<'_> {
+    match (session_id, last_seq) {
+        (Some(session_id), Some(seq)) => Handshake::Resume { session_id, seq },
+        _ => Handshake::Identify,
+    }
+}
+
+/// Returns whether a session that Discord invalidated can be resumed on
+/// the same connection. If not, it has to identify again on a new one.
+pub(crate) fn resumes_invalid_session(
+    resumable: bool,
+    session_id: Option<&str>,
+    last_seq: Option<u64>,
+) -> bool {
+    resumable && handshake(session_id, last_seq) != Handshake::Identify
+}
+
+/// A human readable description of a gateway close code.
+pub(crate) fn describe(code: u16) -> &'static str {
+    match code {
+        4000 => "Unknown error",
+        4001 => "Unknown opcode",
+        4002 => "Decode error",
+        4003 => "Not authenticated",
+        4004 => "Authentication failed",
+        4005 => "Already authenticated",
+        4007 => "Invalid seq",
+        4008 => "Rate limited",
+        4009 => "Session timed out",
+        4010 => "Invalid shard",
+        4011 => "Sharding required",
+        4012 => "Invalid API version",
+        4013 => "Invalid intents",
+        4014 => "Disallowed intents",
+        _ => "Unknown close code",
+    }
+}
+
+/// Exponential backoff with jitter, used to space out reconnect attempts.
+pub(crate) struct Backoff {
+    attempt: u32,
+    base: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(base: Duration, max: Duration) -> Self {
+        Backoff {
+            attempt: 0,
+            base,
+            max,
+        }
+    }
+
+    /// Returns how long to wait before the next attempt.
+    ///
+    /// The delay doubles on every attempt until it reaches the maximum, and
+    /// a random half of it is jittered away so that many clients that lost
+    /// their connection at the same time don't all come back at once.
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let exponential = self
+            .base
+            .checked_mul(2u32.saturating_pow(self.attempt))
+            .unwrap_or(self.max)
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let half = exponential.as_millis() as u64 / 2;
+        let jitter = rand::thread_rng().gen_range(0, half + 1);
+        Duration::from_millis(half + jitter)
+    }
+
+    /// Starts counting attempts from zero again, after a successful connection.
+    pub(crate) fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(Duration::from_secs(1), Duration::from_secs(120))
+    }
+}
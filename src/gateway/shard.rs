@@ -0,0 +1,231 @@
+use super::encoding::Encoding;
+use super::status::{ShardMetrics, ShardStatus};
+use super::waiters::EventWaiters;
+use super::{EventDispatcher, EventHandler, Gateway, GatewayConfig};
+use crate::api::gateway::{GatewayOpcode, Payload};
+use crate::api::id::GuildId;
+use crate::errors::{GatewayError, Result};
+use async_trait::async_trait;
+use futures_util::future;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// The shard a gateway connection belongs to.
+///
+/// https://discordapp.com/developers/docs/topics/gateway#sharding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardInfo {
+    pub id: u32,
+    pub total: u32,
+}
+
+impl ShardInfo {
+    /// Checks if the events of the given guild are sent to this shard.
+    pub fn has_guild(&self, guild: GuildId) -> bool {
+        ((guild.0).0 >> 22) % u64::from(self.total.max(1)) == u64::from(self.id)
+    }
+}
+
+/// How many shards to run, and which ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shards {
+    /// Run as many shards as Discord recommends for the bot.
+    Recommended,
+    /// Run every shard out of the given total.
+    Fixed(u32),
+    /// Run only some of the shards out of the given total. Useful when
+    /// the shards of a bot are split between multiple processes.
+    Range {
+        ids: std::ops::Range<u32>,
+        total: u32,
+    },
+}
+
+impl Default for Shards {
+    fn default() -> Self {
+        Shards::Recommended
+    }
+}
+
+impl Shards {
+    /// Gets the shards that should be started, given the
+    /// recommended shard count from `/gateway/bot`.
+    ///
+    /// Fails if no shard would be started, or if a shard id is not
+    /// below the total.
+    pub(crate) fn resolve(&self, recommended: u32) -> Result<Vec<ShardInfo>> {
+        let (ids, total) = match self {
+            Shards::Recommended => (0..recommended.max(1), recommended.max(1)),
+            Shards::Fixed(total) => (0..*total, *total),
+            Shards::Range { ids, total } => (ids.clone(), *total),
+        };
+
+        if ids.start >= ids.end {
+            return Err(GatewayError::InvalidShards {
+                what: format!("{:?} has no shards to start", self),
+            }
+            .into());
+        }
+        if ids.end > total {
+            return Err(GatewayError::InvalidShards {
+                what: format!("shard {} is out of {} shards", ids.end - 1, total),
+            }
+            .into());
+        }
+
+        Ok(ids.map(|id| ShardInfo { id, total }).collect())
+    }
+}
+
+/// Sends commands, like presence updates, to the gateway connection
+/// of a single shard.
+#[derive(Clone)]
+pub(crate) struct ShardHandle {
+    pub info: ShardInfo,
+    commands: mpsc::UnboundedSender<Payload>,
+    encoding: Encoding,
+    metrics: Arc<ShardMetrics>,
+}
+
+impl ShardHandle {
+    /// Queues a command for the shard.
+    ///
+    /// Commands sent before the shard is ready are held back until
+    /// it identifies or resumes, and commands over the rate limit wait
+    /// in the queue of the connection.
+    pub(crate) fn send(&self, op: GatewayOpcode, value: serde_json::Value) -> Result<()> {
+        let payload = Payload {
+            op: op as u8,
+            d: value,
+            s: None,
+            t: None,
+        };
+        self.encoding.encode_checked(&payload)?;
+
+        self.commands
+            .send(payload)
+            .map_err(|_| crate::errors::GatewayError::ConnectError)?;
+        Ok(())
+    }
+
+    /// Gets the current health of the connection.
+    pub(crate) fn status(&self) -> ShardStatus {
+        self.metrics.status(self.info)
+    }
+}
+
+/// Passes the events of a single shard on to the `ShardManager`.
+struct ShardForwarder {
+    sender: mpsc::UnboundedSender<(ShardInfo, String, serde_json::Value)>,
+    waiters: EventWaiters,
+}
+
+#[async_trait(?Send)]
+impl EventHandler for ShardForwarder {
+    async fn handle(
+        &mut self,
+        shard: ShardInfo,
+        event: String,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        // The event handler might be waiting for this event, so it can't
+        // wait for its turn there.
+        self.waiters.handle_event(&event, &data);
+        self.sender
+            .send((shard, event, data))
+            .map_err(|_| crate::errors::GatewayError::ConnectError)?;
+        Ok(())
+    }
+}
+
+/// Runs a gateway connection for each shard, and routes the events of
+/// all of them into a single event handler.
+pub(crate) struct ShardManager<F>
+where
+    F: EventDispatcher,
+{
+    config: GatewayConfig,
+    shards: Vec<(
+        ShardInfo,
+        mpsc::UnboundedReceiver<Payload>,
+        Arc<ShardMetrics>,
+    )>,
+    event_handler: F,
+}
+
+impl<F> ShardManager<F>
+where
+    F: EventDispatcher,
+{
+    /// Creates a manager for the given shards, along with the handles
+    /// that send commands to them once they are running.
+    pub(crate) fn new(
+        config: GatewayConfig,
+        shards: Vec<ShardInfo>,
+        event_handler: F,
+    ) -> (Self, Vec<ShardHandle>) {
+        let (shards, handles) = shards
+            .into_iter()
+            .map(|info| {
+                let (commands, receiver) = mpsc::unbounded_channel();
+                let metrics = Arc::new(ShardMetrics::default());
+                let handle = ShardHandle {
+                    info,
+                    commands,
+                    encoding: config.encoding,
+                    metrics: metrics.clone(),
+                };
+                ((info, receiver, metrics), handle)
+            })
+            .unzip();
+
+        let manager = ShardManager {
+            config,
+            shards,
+            event_handler,
+        };
+        (manager, handles)
+    }
+
+    /// Starts every shard, and handles their events.
+    ///
+    /// Like `Gateway::handle`, this only returns if one of the shards
+    /// fails in a way that can't be fixed by reconnecting.
+    pub(crate) async fn run(self) -> Result<()> {
+        let ShardManager {
+            config,
+            shards,
+            mut event_handler,
+        } = self;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        log::info!("Starting {} shard(s)", shards.len());
+        let gateways = shards
+            .into_iter()
+            .map(|(shard, commands, metrics)| {
+                let forwarder = ShardForwarder {
+                    sender: sender.clone(),
+                    waiters: config.waiters.clone(),
+                };
+                let config = config.clone();
+                async move {
+                    let mut gateway =
+                        Gateway::new(config, shard, commands, metrics, forwarder).await;
+                    gateway.handle().await
+                }
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        let dispatch = async move {
+            while let Some((shard, event, data)) = receiver.recv().await {
+                event_handler.handle(shard, event, data).await;
+            }
+        };
+
+        // Dispatching ends once every gateway is gone
+        let (result, ()) = future::join(future::try_join_all(gateways), dispatch).await;
+        result?;
+        Ok(())
+    }
+}
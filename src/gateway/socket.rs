@@ -1,8 +1,17 @@
+use super::encoding::Encoding;
+use super::inflate::Inflater;
+use super::ratelimit::{TokenBucket, RESERVED};
+use super::status::ShardMetrics;
 use crate::api::gateway;
 use crate::errors::Result;
 use futures_util::stream::StreamExt;
-use futures_util::SinkExt;
-use websocket_lite::{AsyncClient, AsyncNetworkStream, Message};
+use futures_util::{FutureExt, SinkExt};
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use websocket_lite::{AsyncClient, AsyncNetworkStream, Message, Opcode};
 type WSClient = AsyncClient<Box<dyn AsyncNetworkStream + Send + Sync + Unpin>>;
 
 pub(crate) struct Client {
@@ -31,15 +40,28 @@ pub(crate) struct Connection {
     send: tokio::sync::mpsc::UnboundedSender<gateway::Payload>, // Linked to Client.receive
     receive: futures_util::stream::Fuse<tokio::sync::mpsc::UnboundedReceiver<gateway::Payload>>, // Linked to Client.send
     disconnect: futures_util::stream::Fuse<tokio::sync::mpsc::UnboundedReceiver<bool>>, // Linked to Client.send
+    inflater: Option<Inflater>,
+    encoding: Encoding,
+    bucket: TokenBucket,
+    /// Heartbeats, identifies and resumes, which can use the reserved tokens
+    urgent: VecDeque<gateway::Payload>,
+    queue: VecDeque<gateway::Payload>,
+    metrics: Arc<ShardMetrics>,
 }
 
 impl Connection {
     /// Creates a new Connection structure, which will be used once
     /// to start the socket handler, and a Client structure, which
     /// holds the queues for communication.
+    ///
+    /// If `compress` is set, binary messages are decompressed as a zlib-stream.
+    /// Payloads are decoded and encoded with the given `encoding`.
     pub(crate) fn new(
         socket: WSClient,
         disconnect: tokio::sync::mpsc::UnboundedReceiver<bool>,
+        compress: bool,
+        encoding: Encoding,
+        metrics: Arc<ShardMetrics>,
     ) -> (Self, Client) {
         let (client_send, receive) = tokio::sync::mpsc::unbounded_channel();
         let (send, client_receive) = tokio::sync::mpsc::unbounded_channel();
@@ -50,6 +72,16 @@ impl Connection {
                 receive: receive.fuse(),
                 socket: socket.fuse(),
                 disconnect: disconnect.fuse(),
+                inflater: if compress {
+                    Some(Inflater::new())
+                } else {
+                    None
+                },
+                encoding,
+                bucket: TokenBucket::for_gateway(Instant::now()),
+                urgent: VecDeque::new(),
+                queue: VecDeque::new(),
+                metrics,
             },
             Client {
                 send: client_send,
@@ -60,9 +92,25 @@ impl Connection {
 
     /// Starts the socket handling loop, and immediately returns
     /// The handler is run through a spawned future (tokio::spawn)
-    pub(crate) fn run(mut self) {
+    ///
+    /// The returned handle resolves when the connection is gone, with
+    /// the close frame Discord sent, if there was one.
+    ///
+    /// Outgoing payloads are rate limited, and wait in a queue while the
+    /// limit is reached.
+    pub(crate) fn run(mut self) -> JoinHandle<Result<Option<CloseFrame>>> {
         tokio::spawn(async move {
             loop {
+                let wait = self.wait_time();
+                let refill = async move {
+                    match wait {
+                        Some(wait) => tokio::time::delay_for(wait).await,
+                        None => futures_util::future::pending().await,
+                    }
+                }
+                .fuse();
+                futures_util::pin_mut!(refill);
+
                 futures_util::select! {
                     disconnect = self.disconnect.select_next_some() => {
                         if disconnect {
@@ -72,39 +120,153 @@ impl Connection {
                             break;
                         }
                     }
-                    payload = self.socket.select_next_some() => {
+                    payload = self.socket.next() => {
                         match payload {
-                            Ok(payload) => {
-                                log::trace!("discord raw payload {:?}", payload);
+                            Some(Ok(message)) => {
+                                log::trace!("discord raw payload {:?}", message);
+
+                                if message.opcode() == Opcode::Close {
+                                    let frame = CloseFrame::parse(message.data());
+                                    log::warn!("Discord closed the connection: {:?}", frame);
+                                    return Ok(frame);
+                                }
 
-                                if let Some(data) = payload.as_text() {
-                                    let payload: gateway::Payload = serde_json::from_str::<gateway::Payload>(data)?;
+                                if let Some(payload) = self.decode(&message)? {
                                     self.send.send(payload)?;
-                                } else {
-                                    log::error!("Discord weird payload: {:?}", payload);
                                 }
                             }
-                            Err(e) => log::error!("Discord error: {}", e),
+                            Some(Err(e)) => {
+                                log::error!("Discord error: {}", e);
+                                break;
+                            }
+                            None => {
+                                log::warn!("Discord connection ended");
+                                break;
+                            }
                         }
                     }
                     payload = self.receive.select_next_some() => {
-                        send(payload, &mut self.socket).await?;
+                        if is_urgent(&payload) {
+                            self.urgent.push_back(payload);
+                        } else {
+                            self.queue.push_back(payload);
+                        }
+                        self.flush().await?;
                     }
+                    _ = refill => self.flush().await?,
                 }
             }
-            Ok::<(), crate::errors::DiscordError>(())
-        });
+            self.metrics.queue_depth.store(0, Ordering::Relaxed);
+            Ok(None)
+        })
+    }
+
+    /// Sends as many of the queued payloads as the rate limit allows.
+    async fn flush(&mut self) -> Result<()> {
+        while !self.urgent.is_empty() && self.bucket.try_take(0, Instant::now()) {
+            if let Some(payload) = self.urgent.pop_front() {
+                send(payload, self.encoding, &mut self.socket).await?;
+            }
+        }
+        while self.urgent.is_empty()
+            && !self.queue.is_empty()
+            && self.bucket.try_take(RESERVED, Instant::now())
+        {
+            if let Some(payload) = self.queue.pop_front() {
+                send(payload, self.encoding, &mut self.socket).await?;
+            }
+        }
+
+        let depth = self.urgent.len() + self.queue.len();
+        if depth > 0 {
+            log::debug!("Gateway rate limit reached, {} payload(s) queued", depth);
+        }
+        self.metrics.queue_depth.store(depth, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Gets how long until the next queued payload can be sent, if
+    /// anything is queued.
+    fn wait_time(&mut self) -> Option<std::time::Duration> {
+        let now = Instant::now();
+        if !self.urgent.is_empty() {
+            Some(self.bucket.wait_time(0, now))
+        } else if !self.queue.is_empty() {
+            Some(self.bucket.wait_time(RESERVED, now))
+        } else {
+            None
+        }
+    }
+
+    /// Turns a web socket message into a gateway payload, if the
+    /// message completes one.
+    fn decode(&mut self, message: &Message) -> Result<Option<gateway::Payload>> {
+        match (message.opcode(), self.inflater.as_mut()) {
+            (Opcode::Binary, Some(inflater)) => match inflater.push(message.data())? {
+                Some(data) => Ok(Some(self.encoding.decode(&data)?)),
+                None => Ok(None),
+            },
+            (Opcode::Text, _) | (Opcode::Binary, None) => {
+                Ok(Some(self.encoding.decode(message.data())?))
+            }
+            _ => {
+                log::error!("Discord weird payload: {:?}", message);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The status code and reason of a web socket close frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// Parses the body of a close frame, which is a big endian status code
+    /// optionally followed by an UTF-8 reason.
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 2 {
+            return None;
+        }
+
+        Some(CloseFrame {
+            code: u16::from_be_bytes([data[0], data[1]]),
+            reason: String::from_utf8_lossy(&data[2..]).into_owned(),
+        })
     }
 }
 
+/// Checks if the payload is one of those that keep the connection alive,
+/// which are sent before any other queued payload.
+fn is_urgent(payload: &gateway::Payload) -> bool {
+    payload.op == gateway::GatewayOpcode::Heartbeat as u8
+        || payload.op == gateway::GatewayOpcode::Identify as u8
+        || payload.op == gateway::GatewayOpcode::Resume as u8
+}
+
 async fn send(
     mut payload: gateway::Payload,
+    encoding: Encoding,
     socket: &mut futures_util::stream::Fuse<WSClient>,
 ) -> Result<()> {
-    let payload_str = serde_json::to_string(&payload)?;
+    let message = match encoding.encode_checked(&payload) {
+        Ok(message) => message,
+        // Without these the connection is useless, so end it instead
+        Err(e) if is_urgent(&payload) => return Err(e),
+        Err(e) => {
+            // Sending it would get the connection closed, so drop it instead
+            log::error!("Not sending gateway payload (op {}): {}", payload.op, e);
+            return Ok(());
+        }
+    };
 
     // Don't log the discord token
-    if payload.op == gateway::GatewayOpcode::Identify as u8 {
+    if payload.op == gateway::GatewayOpcode::Identify as u8
+        || payload.op == gateway::GatewayOpcode::Resume as u8
+    {
         if let Some(token) = payload.d.get_mut("token") {
             *token = serde_json::json!("<TOKEN REDACTED>");
         }
@@ -113,8 +275,11 @@ async fn send(
             serde_json::to_string(&payload)?
         );
     } else {
-        log::trace!("sending gateway payload: {}", payload_str);
+        log::trace!(
+            "sending gateway payload: {}",
+            serde_json::to_string(&payload)?
+        );
     }
-    socket.send(Message::text(payload_str)).await?;
+    socket.send(message).await?;
     Ok(())
 }
@@ -0,0 +1,120 @@
+use super::ShardInfo;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How many heartbeats the average latency is calculated from
+const LATENCY_SAMPLES: usize = 10;
+
+/// What the gateway connection of a shard is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// Waiting to identify, or opening the web socket
+    Connecting,
+    /// Sent an Identify, waiting for Discord to send READY
+    Identifying,
+    /// Sent a Resume, waiting for Discord to send RESUMED
+    Resuming,
+    /// Connected, and receiving events
+    Connected,
+    /// The connection dropped, waiting before connecting again
+    Reconnecting,
+    /// The connection was closed in a way that reconnecting can't fix
+    Disconnected,
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        ConnectionState::Connecting
+    }
+}
+
+/// The health of the gateway connection of a shard, from `Discord::shard_status`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ShardStatus {
+    pub shard: ShardInfo,
+    pub state: ConnectionState,
+    /// The round trip time of the last acknowledged heartbeat
+    pub latency: Option<Duration>,
+    /// The average round trip time of the last few heartbeats
+    pub average_latency: Option<Duration>,
+    /// How many times the connection was replaced by a new one
+    pub reconnects: u64,
+    /// How many times the session was resumed successfully
+    pub resumes: u64,
+    /// How many payloads are waiting for the gateway rate limit
+    pub queue_depth: usize,
+}
+
+#[derive(Default)]
+struct Heartbeats {
+    sent_at: Option<Instant>,
+    latencies: VecDeque<Duration>,
+}
+
+/// Information about the connection of a shard, which is kept up to
+/// date by the connection itself.
+#[derive(Default)]
+pub(crate) struct ShardMetrics {
+    pub queue_depth: AtomicUsize,
+    pub reconnects: AtomicU64,
+    pub resumes: AtomicU64,
+    state: Mutex<ConnectionState>,
+    heartbeats: Mutex<Heartbeats>,
+}
+
+impl ShardMetrics {
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = state;
+    }
+
+    /// Remembers when a heartbeat was sent, to measure the latency once
+    /// it is acknowledged.
+    pub(crate) fn heartbeat_sent(&self, now: Instant) {
+        self.heartbeats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .sent_at = Some(now);
+    }
+
+    pub(crate) fn heartbeat_acked(&self, now: Instant) {
+        let mut heartbeats = self
+            .heartbeats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(sent_at) = heartbeats.sent_at.take() {
+            if heartbeats.latencies.len() == LATENCY_SAMPLES {
+                heartbeats.latencies.pop_front();
+            }
+            heartbeats
+                .latencies
+                .push_back(now.saturating_duration_since(sent_at));
+        }
+    }
+
+    pub(crate) fn status(&self, shard: ShardInfo) -> ShardStatus {
+        let heartbeats = self
+            .heartbeats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let average_latency = if heartbeats.latencies.is_empty() {
+            None
+        } else {
+            let total: Duration = heartbeats.latencies.iter().sum();
+            Some(total / heartbeats.latencies.len() as u32)
+        };
+
+        ShardStatus {
+            shard,
+            state: *self.state.lock().unwrap_or_else(PoisonError::into_inner),
+            latency: heartbeats.latencies.back().copied(),
+            average_latency,
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            resumes: self.resumes.load(Ordering::Relaxed),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+        }
+    }
+}
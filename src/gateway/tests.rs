@@ -0,0 +1,407 @@
+use super::heartbeat::HeartbeatHandler;
+use super::inflate::Inflater;
+use super::reconnect::{self, Backoff, Handshake};
+use super::shard::{ShardInfo, Shards};
+use super::socket::CloseFrame;
+use crate::api::gateway::SessionStartLimit;
+use std::time::Duration;
+
+#[test]
+fn test_close_frame() {
+    assert_eq!(
+        CloseFrame::parse(&[0x0f, 0xa4, b'b', b'a', b'd']),
+        Some(CloseFrame {
+            code: 4004,
+            reason: String::from("bad"),
+        })
+    );
+    assert_eq!(CloseFrame::parse(&[]), None);
+}
+
+#[test]
+fn test_close_codes() {
+    assert!(reconnect::is_fatal(4004));
+    assert!(reconnect::is_fatal(4011));
+    assert!(reconnect::is_fatal(4012));
+    assert!(reconnect::is_fatal(4014));
+    assert!(!reconnect::is_fatal(4000));
+    assert!(!reconnect::is_fatal(1006));
+
+    assert!(reconnect::invalidates_session(4009));
+    assert!(!reconnect::invalidates_session(4000));
+}
+
+#[test]
+fn test_handshake() {
+    let session = Some("session");
+    assert_eq!(
+        reconnect::handshake(session, Some(5)),
+        Handshake::Resume {
+            session_id: "session",
+            seq: 5,
+        }
+    );
+    // Nothing to resume without a session, or without a single event
+    assert_eq!(reconnect::handshake(None, Some(5)), Handshake::Identify);
+    assert_eq!(reconnect::handshake(session, None), Handshake::Identify);
+    assert_eq!(reconnect::handshake(None, None), Handshake::Identify);
+
+    // A new connection keeps the sequence number, so it can still resume
+    let (disconnect, _) = tokio::sync::mpsc::unbounded_channel();
+    let (handler, _) = HeartbeatHandler::new(disconnect, Some(5));
+    let last_seq = *handler.last_seq.borrow();
+    assert_eq!(
+        reconnect::handshake(session, last_seq),
+        Handshake::Resume {
+            session_id: "session",
+            seq: 5,
+        }
+    );
+}
+
+#[test]
+fn test_invalid_session() {
+    let session = Some("session");
+    assert!(reconnect::resumes_invalid_session(true, session, Some(5)));
+    assert!(!reconnect::resumes_invalid_session(false, session, Some(5)));
+    // Without a session, or a sequence number, there is nothing to resume
+    assert!(!reconnect::resumes_invalid_session(true, None, Some(5)));
+    assert!(!reconnect::resumes_invalid_session(true, session, None));
+}
+
+#[test]
+fn test_backoff() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(8));
+
+    for max in &[1000, 2000, 4000, 8000, 8000] {
+        let delay = backoff.next_delay().as_millis() as u64;
+        assert!(delay >= max / 2 && delay <= *max, "{} not in range", delay);
+    }
+
+    backoff.reset();
+    assert!(backoff.next_delay() <= Duration::from_secs(1));
+}
+
+#[test]
+fn test_shards_resolve() {
+    let shard = |id, total| ShardInfo { id, total };
+
+    assert_eq!(
+        Shards::Recommended.resolve(2).unwrap(),
+        vec![shard(0, 2), shard(1, 2)]
+    );
+    assert_eq!(Shards::Recommended.resolve(0).unwrap(), vec![shard(0, 1)]);
+    assert_eq!(Shards::Fixed(1).resolve(4).unwrap(), vec![shard(0, 1)]);
+    assert_eq!(
+        Shards::Range {
+            ids: 2..4,
+            total: 8
+        }
+        .resolve(1)
+        .unwrap(),
+        vec![shard(2, 8), shard(3, 8)]
+    );
+
+    assert!(Shards::Fixed(0).resolve(1).is_err());
+    let range = |ids, total| Shards::Range { ids, total };
+    assert!(range(3..3, 8).resolve(1).is_err());
+    assert!(range(6..9, 8).resolve(1).is_err());
+}
+
+fn start_limit(remaining: u32, reset_after: u32, max_concurrency: u32) -> SessionStartLimit {
+    SessionStartLimit {
+        total: 1000,
+        remaining,
+        reset_after,
+        max_concurrency,
+    }
+}
+
+#[tokio::test]
+async fn test_identify_budget() {
+    use super::identify::{IdentifyQueue, LocalIdentifyQueue};
+    use tokio::time::Instant;
+
+    tokio::time::pause();
+    let queue = LocalIdentifyQueue::new();
+    queue.set_limit(start_limit(1, 60_000, 2)).await;
+
+    let start = Instant::now();
+    queue.acquire(ShardInfo { id: 0, total: 2 }).await.unwrap();
+    assert_eq!(start.elapsed().as_secs(), 0);
+    // The budget is used up, so the next identify waits until it resets
+    queue.acquire(ShardInfo { id: 1, total: 2 }).await.unwrap();
+    assert_eq!(start.elapsed().as_secs(), 60);
+}
+
+#[tokio::test]
+async fn test_identify_buckets() {
+    use super::identify::{IdentifyQueue, LocalIdentifyQueue};
+    use tokio::time::Instant;
+
+    tokio::time::pause();
+    let queue = LocalIdentifyQueue::new();
+    queue.set_limit(start_limit(100, 60_000, 2)).await;
+
+    let start = Instant::now();
+    let identified = futures_util::future::join_all((0..4).map(|id| {
+        let queue = &queue;
+        async move {
+            queue.acquire(ShardInfo { id, total: 4 }).await.unwrap();
+            start.elapsed().as_secs()
+        }
+    }))
+    .await;
+    // Shards 0 and 2 share a bucket, and so do 1 and 3
+    assert_eq!(identified, vec![0, 0, 5, 5]);
+
+    // With a single bucket, every shard waits for the last identify
+    queue.set_limit(start_limit(100, 60_000, 1)).await;
+    queue.acquire(ShardInfo { id: 1, total: 4 }).await.unwrap();
+    assert_eq!(start.elapsed().as_secs(), 10);
+}
+
+#[test]
+fn test_inflate_stream() {
+    use flate2::{Compress, Compression, FlushCompress};
+
+    let mut compress = Compress::new(Compression::default(), true);
+    let mut deflate = |data: &[u8]| {
+        let mut output = Vec::with_capacity(data.len() + 64);
+        compress
+            .compress_vec(data, &mut output, FlushCompress::Sync)
+            .unwrap();
+        output
+    };
+
+    let first = deflate(br#"{"op":10,"d":{"heartbeat_interval":41250},"s":null,"t":null}"#);
+    let second = deflate(br#"{"op":11,"d":null,"s":null,"t":null}"#);
+
+    let mut inflater = Inflater::new();
+
+    // A message split over two frames only comes out after the second one
+    let (head, tail) = first.split_at(first.len() / 2);
+    assert_eq!(inflater.push(head).unwrap(), None);
+    let message = inflater.push(tail).unwrap().unwrap();
+    assert_eq!(
+        message,
+        br#"{"op":10,"d":{"heartbeat_interval":41250},"s":null,"t":null}"#.to_vec()
+    );
+
+    // Later messages depend on the same zlib context
+    let message = inflater.push(&second).unwrap().unwrap();
+    assert_eq!(message, br#"{"op":11,"d":null,"s":null,"t":null}"#.to_vec());
+
+    // Messages that compress well need more room than the first guess
+    let big = vec![b'a'; 100_000];
+    let message = inflater.push(&deflate(&big)).unwrap().unwrap();
+    assert_eq!(message, big);
+}
+
+#[cfg(feature = "etf")]
+#[test]
+fn test_etf_payload() {
+    use super::encoding::Encoding;
+    use serde_json::json;
+
+    fn atom(output: &mut Vec<u8>, name: &str) {
+        output.extend_from_slice(&[115, name.len() as u8]);
+        output.extend_from_slice(name.as_bytes());
+    }
+
+    // {op: 0, s: 42, t: 'READY', d: {id: 80351110224678912, name: <<"bot">>, bot: true, avatar: nil}}
+    let mut data = vec![131, 116, 0, 0, 0, 4];
+    atom(&mut data, "op");
+    data.extend_from_slice(&[97, 0]);
+    atom(&mut data, "s");
+    data.extend_from_slice(&[98, 0, 0, 0, 42]);
+    atom(&mut data, "t");
+    atom(&mut data, "READY");
+    atom(&mut data, "d");
+    data.extend_from_slice(&[116, 0, 0, 0, 5]);
+    atom(&mut data, "id");
+    data.extend_from_slice(&[110, 8, 0]);
+    data.extend_from_slice(&80_351_110_224_678_912u64.to_le_bytes());
+    atom(&mut data, "name");
+    data.extend_from_slice(&[109, 0, 0, 0, 3]);
+    data.extend_from_slice(b"bot");
+    atom(&mut data, "bot");
+    atom(&mut data, "true");
+    atom(&mut data, "avatar");
+    atom(&mut data, "nil");
+    // Atoms used as keys stay strings
+    atom(&mut data, "nil");
+    atom(&mut data, "false");
+
+    let payload = Encoding::Etf.decode(&data).unwrap();
+    assert_eq!(payload.op, 0);
+    assert_eq!(payload.s, Some(42));
+    assert_eq!(payload.t.as_deref(), Some("READY"));
+    assert_eq!(
+        payload.d,
+        json!({
+            "id": 80_351_110_224_678_912u64,
+            "name": "bot",
+            "bot": true,
+            "avatar": null,
+            "nil": false,
+        })
+    );
+
+    let value = json!({
+        "small": 7,
+        "int": -70000,
+        "big": u64::MAX,
+        "negative": i64::MIN,
+        "float": 1.5,
+        "list": [[], "a", null, false],
+    });
+    let payload = crate::api::gateway::Payload {
+        op: 3,
+        d: value.clone(),
+        s: None,
+        t: None,
+    };
+    let encoded = Encoding::Etf.encode(&payload).unwrap();
+    let decoded = Encoding::Etf.decode(encoded.data()).unwrap();
+    assert_eq!(decoded.op, 3);
+    assert_eq!(decoded.d, value);
+    assert_eq!(decoded.s, None);
+    assert_eq!(decoded.t, None);
+
+    assert!(super::etf::decode(&[131, 109, 0, 0, 0, 9, b'a']).is_err());
+}
+
+#[test]
+fn test_token_bucket() {
+    use super::ratelimit::TokenBucket;
+    use std::time::Instant;
+
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(3, Duration::from_secs(3), start);
+    assert!(bucket.try_take(1, start));
+    assert!(bucket.try_take(1, start));
+    // The last token is reserved
+    assert!(!bucket.try_take(1, start));
+    assert_eq!(bucket.wait_time(1, start), Duration::from_secs(1));
+    assert!(bucket.try_take(0, start));
+    assert!(!bucket.try_take(0, start));
+
+    let later = start + Duration::from_millis(1500);
+    assert!(bucket.try_take(0, later));
+    assert!(!bucket.try_take(0, later));
+    assert_eq!(bucket.wait_time(0, later), Duration::from_millis(500));
+
+    // It never fills over the capacity
+    let much_later = start + Duration::from_secs(60);
+    assert_eq!(bucket.wait_time(2, much_later), Duration::from_secs(0));
+    assert_eq!(bucket.wait_time(3, much_later), Duration::from_secs(1));
+}
+
+#[test]
+fn test_payload_size_limit() {
+    use super::encoding::Encoding;
+    use crate::api::gateway::Payload;
+
+    let payload = |size: usize| Payload {
+        op: 3,
+        d: serde_json::Value::String("a".repeat(size)),
+        s: None,
+        t: None,
+    };
+    assert!(Encoding::Json.encode_checked(&payload(100)).is_ok());
+    assert!(matches!(
+        Encoding::Json.encode_checked(&payload(5000)),
+        Err(crate::DiscordError::GatewayError(
+            crate::errors::GatewayError::PayloadTooLarge { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_member_requests() {
+    use super::members::MemberRequests;
+    use crate::api::guild::MemberChunk;
+    use serde_json::json;
+
+    let requests = MemberRequests::default();
+    let (nonce, mut receiver) = requests.register();
+    let (other_nonce, _other) = requests.register();
+    assert_ne!(nonce, other_nonce);
+
+    let chunk = |index: u32, user: &str, not_found: Vec<&str>| -> MemberChunk {
+        serde_json::from_value(json!({
+            "guild_id": "41771983423143937",
+            "members": [{
+                "user": {"id": user, "username": "member", "discriminator": "0001", "avatar": null},
+                "nick": null,
+                "roles": [],
+                "joined_at": "2015-04-26T06:26:56.936000+00:00",
+                "deaf": false,
+                "mute": false
+            }],
+            "chunk_index": index,
+            "chunk_count": 2,
+            "not_found": not_found,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    };
+
+    requests.handle_chunk(chunk(1, "2", vec!["3"]));
+    assert!(receiver.try_recv().is_err());
+    requests.handle_chunk(chunk(0, "1", vec![]));
+
+    let members = receiver.try_recv().unwrap();
+    assert_eq!(members.members.len(), 2);
+    assert_eq!(members.not_found.len(), 1);
+    assert!(members.presences.is_empty());
+}
+
+#[test]
+fn test_shard_has_guild() {
+    let guild: crate::api::id::GuildId =
+        serde_json::from_value("41771983423143937".into()).unwrap();
+    // (41771983423143937 >> 22) % 4 == 2
+    assert!(ShardInfo { id: 2, total: 4 }.has_guild(guild));
+    assert!(!ShardInfo { id: 0, total: 4 }.has_guild(guild));
+    assert!(ShardInfo { id: 0, total: 1 }.has_guild(guild));
+}
+
+#[test]
+fn test_heartbeat_latency() {
+    use super::status::{ConnectionState, ShardMetrics};
+    use std::time::Instant;
+
+    let shard = ShardInfo { id: 0, total: 1 };
+    let metrics = ShardMetrics::default();
+    let status = metrics.status(shard);
+    assert_eq!(status.state, ConnectionState::Connecting);
+    assert_eq!(status.latency, None);
+    assert_eq!(status.average_latency, None);
+
+    let start = Instant::now();
+    metrics.heartbeat_sent(start);
+    metrics.heartbeat_acked(start + Duration::from_millis(100));
+    // An ack without a heartbeat is not measured
+    metrics.heartbeat_acked(start + Duration::from_millis(150));
+    metrics.heartbeat_sent(start + Duration::from_secs(40));
+    metrics.heartbeat_acked(start + Duration::from_millis(40_200));
+    metrics.set_state(ConnectionState::Connected);
+
+    let status = metrics.status(shard);
+    assert_eq!(status.state, ConnectionState::Connected);
+    assert_eq!(status.latency, Some(Duration::from_millis(200)));
+    assert_eq!(status.average_latency, Some(Duration::from_millis(150)));
+
+    // Only the last few heartbeats count towards the average
+    for i in 0..20 {
+        let sent = start + Duration::from_secs(100 + i);
+        metrics.heartbeat_sent(sent);
+        metrics.heartbeat_acked(sent + Duration::from_millis(50));
+    }
+    assert_eq!(
+        metrics.status(shard).average_latency,
+        Some(Duration::from_millis(50))
+    );
+}
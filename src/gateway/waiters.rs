@@ -0,0 +1,51 @@
+use serde_json::Value;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError};
+
+type Waiter = Box<dyn FnMut(&str, &Value) -> bool + Send>;
+
+/// Passes events to the collectors from `Discord::collector`.
+///
+/// The events are passed on before they are handled by the event handlers,
+/// so that a handler can wait for an event without blocking it.
+#[derive(Clone, Default)]
+pub(crate) struct EventWaiters {
+    waiters: Arc<Mutex<Vec<Waiter>>>,
+}
+
+impl EventWaiters {
+    /// Adds a waiter, which gets the name and data of every event until
+    /// it returns false.
+    pub(crate) fn register(&self, waiter: impl FnMut(&str, &Value) -> bool + Send + 'static) {
+        self.waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::new(waiter));
+    }
+
+    /// Passes an event to every waiter, and forgets the ones that are done.
+    ///
+    /// Waiters run user code, like the filters of collectors, so a waiter
+    /// that panics is forgotten too, instead of taking the shard down.
+    pub(crate) fn handle_event(&self, event: &str, data: &Value) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut waiting = Vec::with_capacity(waiters.len());
+        for mut waiter in waiters.drain(..) {
+            match panic::catch_unwind(AssertUnwindSafe(|| waiter(event, data))) {
+                Ok(true) => waiting.push(waiter),
+                Ok(false) => {}
+                Err(_) => log::error!("A collector panicked while handling {}", event),
+            }
+        }
+        *waiters = waiting;
+    }
+
+    /// Gets how many waiters are registered.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
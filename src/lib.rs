@@ -7,8 +7,28 @@ mod gateway;
 
 pub mod events;
 
-pub use api::{guild::Guild, user::User};
-pub use discord::Discord;
+pub use api::{
+    channel::{
+        Channel, ChannelPins, ClearedEmojiReactions, ClearedReactions, DeletedMessage,
+        DeletedMessages, Emoji, Message, MessageReaction, PartialMessage, Typing,
+    },
+    gateway::{Ready, SessionStartLimit},
+    guild::{
+        Activity, ActivityTimestamp, ActivityType, Ban, ClientStatus, CreatedInvite, DeletedInvite,
+        DeletedRole, Guild, GuildBan, GuildEmojis, GuildMember, GuildRole, Invite, InviteMetadata,
+        MemberChunk, MemberQuery, NewGuildMember, OnlineStatus, PartialGuildMember, PresenceUpdate,
+        RemovedGuildMember, RequestedMembers, Role, UnavailableGuild, UpdatedGuildMember,
+        UpdatedIntegrations, UpdatedWebhooks,
+    },
+    id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+    intents::GatewayIntents,
+    user::User,
+};
+pub use discord::{Discord, DiscordHandle};
 pub use errors::{DiscordError, Result};
+pub use gateway::encoding::Encoding;
+pub use gateway::identify::{IdentifyQueue, LocalIdentifyQueue};
+pub use gateway::shard::{ShardInfo, Shards};
+pub use gateway::status::{ConnectionState, ShardStatus};
 
 const LIBRARY_IDENTITY: &str = "Thatcord";